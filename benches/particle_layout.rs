@@ -0,0 +1,85 @@
+//! Compares the two layouts on the hot loops of a step, integration and the
+//! broadphase reads, over `Vec<Particle>` and over the `ParticleStore` arrays.
+//! The whole brownian step is only timed with the store, for the thread
+//! counts, since the simulation no longer has a `Vec<Particle>` step to
+//! compare with. Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use simple_fluid_simulation::benchmark;
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::particle_store::ParticleStore;
+use simple_fluid_simulation::simulation_factory::ScenarioParams;
+use simple_fluid_simulation::simulation_factory::brownian_motion_sim;
+use simple_fluid_simulation::vector2::Vector2;
+
+const DT: f64 = 0.001;
+const PASSES: usize = 20_000;
+
+fn time(name: &str, mut pass: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    for _ in 0..PASSES {
+        pass();
+    }
+    let micros = 1e6 * start.elapsed().as_secs_f64() / PASSES as f64;
+    println!("{:<32} {:8.3} us/pass", name, micros);
+    micros
+}
+
+// reads what the broadphase reads, positions and radii
+fn extent_aos(particles: &[Particle]) -> f64 {
+    particles
+        .iter()
+        .map(|p| p.position.x + p.position.y + p.radius)
+        .fold(0.0, f64::max)
+}
+
+fn extent_soa(particles: &ParticleStore) -> f64 {
+    particles
+        .positions
+        .iter()
+        .zip(&particles.radii)
+        .map(|(x, r)| x.x + x.y + r)
+        .fold(0.0, f64::max)
+}
+
+fn main() {
+    let sim = brownian_motion_sim(&ScenarioParams::default()).unwrap();
+    let mut store = sim.particles().clone();
+    let mut particles: Vec<Particle> = store.iter().collect();
+    let gravity = Vector2::new(0.0, -1.0);
+    println!("{} particles, {} passes", particles.len(), PASSES);
+
+    let aos = time("integrate Vec<Particle>", || {
+        for p in particles.iter_mut() {
+            p.velocity += gravity * DT;
+            p.position += p.velocity * DT;
+        }
+        black_box(&particles);
+    });
+    let soa = time("integrate ParticleStore", || {
+        for (x, v) in store.positions.iter_mut().zip(&mut store.velocities) {
+            *v += gravity * DT;
+            *x += *v * DT;
+        }
+        black_box(&store);
+    });
+    println!("{:<32} {:8.2}x", "integration speedup", aos / soa);
+
+    let aos = time("broadphase reads Vec<Particle>", || {
+        black_box(extent_aos(black_box(&particles)));
+    });
+    let soa = time("broadphase reads ParticleStore", || {
+        black_box(extent_soa(black_box(&store)));
+    });
+    println!("{:<32} {:8.2}x", "read speedup", aos / soa);
+
+    for threads in [1, 4] {
+        println!(
+            "brownian step (ParticleStore only), {} threads: {}",
+            threads,
+            benchmark::brownian_motion(2000, threads)
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+use std::time::Instant;
+
+use crate::simulation_factory::ScenarioParams;
+use crate::simulation_factory::brownian_motion_sim;
+
+pub struct BenchmarkResult {
+    pub particles: usize,
+    pub steps: usize,
+    pub seconds: f64,
+}
+
+impl BenchmarkResult {
+    pub fn particle_steps_per_second(&self) -> f64 {
+        (self.particles * self.steps) as f64 / self.seconds
+    }
+}
+
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} particles, {} steps in {:.3} s, {:.3} ms/step, {:.0} particle steps/s",
+            self.particles,
+            self.steps,
+            self.seconds,
+            1000.0 * self.seconds / self.steps as f64,
+            self.particle_steps_per_second()
+        )
+    }
+}
+
+/// Times `steps` updates of the brownian motion scenario, after a short warm up.
+pub fn brownian_motion(steps: usize, threads: usize) -> BenchmarkResult {
+    const DT: f64 = 0.001;
+
+    let mut sim = brownian_motion_sim(&ScenarioParams::default()).expect("default scenario fits");
+    sim.threads = threads;
+    for _ in 0..steps / 10 {
+        sim.update(DT);
+    }
+
+    let start = Instant::now();
+    for _ in 0..steps {
+        sim.update(DT);
+    }
+
+    BenchmarkResult {
+        particles: sim.particles.len(),
+        steps,
+        seconds: start.elapsed().as_secs_f64(),
+    }
+}
@@ -0,0 +1,127 @@
+use macroquad::input::KeyCode;
+use macroquad::input::MouseButton;
+use macroquad::input::is_key_pressed;
+use macroquad::input::is_mouse_button_down;
+use macroquad::input::mouse_position;
+use macroquad::input::mouse_wheel;
+use macroquad::window::screen_height;
+use macroquad::window::screen_width;
+
+use crate::core::ParticleId;
+use crate::core::Rectangle;
+use crate::interaction::particle_at;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+use crate::viewport::Viewport;
+
+const ZOOM_STEP: f64 = 1.15; // per notch of the mouse wheel
+const PICK_TOLERANCE: f64 = 4.0; // pixels
+// limits relative to the view of the scenario, beyond them the transforms lose precision
+const MIN_ZOOM: f64 = 0.1;
+const MAX_ZOOM: f64 = 1000.0;
+
+/// The part of the simulation the window shows, independent of `Simulation::view`.
+///
+/// The mouse wheel zooms around the cursor and dragging with the right button
+/// pans. F follows the particle under the cursor, or the first tracked one,
+/// until F is pressed again or the view is panned. Home goes back to the view
+/// of the scenario.
+pub struct Camera {
+    pub view: Rectangle,
+    home: Rectangle,
+    following: Option<ParticleId>,
+    drag_from: Option<Vector2>, // screen position of the mouse while panning
+}
+
+impl Camera {
+    pub fn new(view: Rectangle) -> Camera {
+        Camera {
+            view,
+            home: view,
+            following: None,
+            drag_from: None,
+        }
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport::new(self.view, screen_width() as f64, screen_height() as f64)
+    }
+
+    /// Reads the mouse and keys, once per frame.
+    pub fn handle_input(&mut self, sim: &Simulation) {
+        let (x, y) = mouse_position();
+        let mouse = Vector2::new(x as f64, y as f64);
+        let viewport = self.viewport();
+
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 {
+            let factor = if wheel > 0.0 {
+                1.0 / ZOOM_STEP
+            } else {
+                ZOOM_STEP
+            };
+            self.zoom(viewport.to_world(mouse), factor);
+        }
+
+        if is_mouse_button_down(MouseButton::Right) {
+            if let Some(from) = self.drag_from {
+                let shift = (mouse - from) / viewport.scale();
+                self.view.min -= shift;
+                self.view.max -= shift;
+                self.following = None;
+            }
+            self.drag_from = Some(mouse);
+        } else {
+            self.drag_from = None;
+        }
+
+        if is_key_pressed(KeyCode::F) {
+            self.following = match self.following {
+                Some(_) => None,
+                None => {
+                    let tolerance = PICK_TOLERANCE / viewport.scale().x;
+                    particle_at(sim, viewport.to_world(mouse), tolerance)
+                        .or_else(|| sim.trails.keys().min().copied())
+                }
+            };
+        }
+        if is_key_pressed(KeyCode::Home) {
+            self.view = self.home;
+            self.following = None;
+        }
+    }
+
+    /// Scales the view by `factor` keeping `center` in place, as far as the
+    /// zoom limits allow.
+    pub fn zoom(&mut self, center: Vector2, factor: f64) {
+        let width = self.view.width();
+        let home = self.home.width();
+        let factor = (width * factor).clamp(home / MAX_ZOOM, home / MIN_ZOOM) / width;
+        self.view.min = center + factor * (self.view.min - center);
+        self.view.max = center + factor * (self.view.max - center);
+    }
+
+    /// Centers the view on the followed particle, after the steps of a frame.
+    pub fn follow(&mut self, sim: &Simulation) {
+        let Some(id) = self.following else {
+            return;
+        };
+        match sim.particle(id) {
+            Some(particle) => {
+                let shift = particle.position - (self.view.min + self.view.max) / 2.0;
+                self.view.min += shift;
+                self.view.max += shift;
+            }
+            None => self.following = None,
+        }
+    }
+
+    /// One line for the HUD.
+    pub fn status(&self) -> String {
+        let zoom = self.home.width() / self.view.width();
+        match self.following {
+            Some(id) => format!("Zoom: {:.2}x, following {}", zoom, id.0),
+            None => format!("Zoom: {:.2}x", zoom),
+        }
+    }
+}
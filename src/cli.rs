@@ -0,0 +1,366 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use simple_fluid_simulation::headless::RunLength;
+use simple_fluid_simulation::rng::SimRng;
+use simple_fluid_simulation::simulation_factory::SCENARIOS;
+use simple_fluid_simulation::simulation_factory::Scenario;
+use simple_fluid_simulation::simulation_factory::ScenarioParams;
+use simple_fluid_simulation::simulation_factory::find_scenario;
+
+pub const USAGE: &str = "\
+Usage: simple_fluid_simulation [OPTIONS] [SCENARIO]
+
+Runs SCENARIO (default: brownian) in a window or headless.
+
+Options:
+  --list               list the scenarios and exit
+  --file PATH          run the scenario described in a file instead
+  --replay PATH        play back an extended XYZ trajectory, e.g. from --xyz
+  --headless           run without a window
+  --steps N            number of steps of a headless run
+  --time T             simulated seconds of a headless run (default 10)
+  --load PATH          resume from a snapshot taken of the same scenario
+  --save PATH          write a snapshot at the end of a headless run,
+                       as text if PATH ends in .txt, else binary
+  --xyz PATH           write an extended XYZ trajectory
+  --vtk BASE           write VTK particle and field files BASE_n.vtk,
+                       BASE_field_n.vtk and ParaView collections BASE.pvd
+  --field-cells N      grid cells along the longer side of the VTK fields
+                       (default 64)
+  --csv PATH           write energy, momentum, temperature, pressure and
+                       tracked positions as CSV
+  --png BASE           render frames offscreen to BASE_00000.png, ...
+  --svg BASE           write vector frames BASE_00000.svg, ...
+  --frame-size WxH     size of the PNG and SVG frames, the view is
+                       letterboxed to fit (default: the window size)
+  --frame-interval N   steps between frames (default: the output interval)
+  --output-interval N  steps between output frames (default 100)
+  --dt DT              time step (default 0.001)
+  --seed N             random seed
+  --count N            number of particles
+  --radius R           particle radius
+  --temperature T      temperature of the initial velocities
+  --gravity G          vertical gravity, negative pulls down
+  --restitution E      coefficient of restitution, 0 to 1
+  --threads N          worker threads for the simulation step
+  --benchmark          time the brownian scenario and exit
+  -h, --help           print this help
+
+Keys in the window:
+  Space                pause or resume
+  N                    advance one step while paused
+  +, -                 double or halve the speed
+  R                    reset to the initial state and stop the output
+  H                    show or hide the info
+  F12                  save the frame as frame_STEP.svg
+  1, 2, 3, 4           mouse tool: grab a particle, push or pull the
+                       particles under the brush, spawn a particle with
+                       the velocity of the drag
+  [, ]                 shrink or grow the brush or spawned particle
+  mouse wheel          zoom around the cursor
+  right drag           pan the view
+  F                    follow the particle under the cursor or a tracked
+                       one, again to stop
+  Home                 back to the view of the scenario
+
+Keys in a replay:
+  Space                play or pause
+  Left, Right          step a frame back or forward
+  Up, Down             double or halve the speed
+  Home, End            jump to the first or last frame
+  click the timeline at the bottom to jump to a time
+";
+
+// smaller particles are invisible in every built-in boundary
+const MIN_RADIUS: f64 = 1e-4;
+
+#[derive(Clone)]
+pub enum Command {
+    Run(Box<RunOptions>),
+    List,
+    Help,
+    Replay(PathBuf),
+    Benchmark { threads: usize },
+}
+
+#[derive(Clone)]
+pub enum ScenarioSource {
+    Builtin(&'static Scenario),
+    File(PathBuf),
+}
+
+#[derive(Clone)]
+pub struct RunOptions {
+    pub scenario: ScenarioSource,
+    pub params: ScenarioParams,
+    pub dt: f64,
+    pub threads: usize,
+    pub headless: Option<RunLength>, // None opens a window
+    pub load: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub xyz: Option<PathBuf>,
+    pub vtk: Option<PathBuf>,
+    pub field_cells: usize,
+    pub csv: Option<PathBuf>,
+    pub png: Option<PathBuf>,
+    pub svg: Option<PathBuf>,
+    pub frame_size: Option<(usize, usize)>,
+    pub frame_interval: Option<u64>,
+    pub output_interval: u64,
+}
+
+/// Parses the arguments without the program name.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let mut scenario = None;
+    let mut file = None;
+    let mut replay = None;
+    let mut load = None;
+    let mut save = None;
+    let mut xyz = None;
+    let mut vtk = None;
+    let mut field_cells = 64;
+    let mut csv = None;
+    let mut png = None;
+    let mut svg = None;
+    let mut frame_size = None;
+    let mut frame_interval = None;
+    let mut output_interval = 100;
+    let mut params = ScenarioParams::default();
+    let mut dt = 0.001;
+    let mut threads = 1;
+    let mut headless = false;
+    let mut length = None;
+    let mut list = false;
+    let mut benchmark = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline_value.clone().or_else(|| args.next().cloned());
+
+        match flag {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--list" => list = true,
+            "--benchmark" => benchmark = true,
+            "--file" => file = Some(PathBuf::from(value().ok_or("--file needs a path")?)),
+            "--replay" => replay = Some(PathBuf::from(value().ok_or("--replay needs a path")?)),
+            "--load" => load = Some(PathBuf::from(value().ok_or("--load needs a path")?)),
+            "--save" => save = Some(PathBuf::from(value().ok_or("--save needs a path")?)),
+            "--xyz" => xyz = Some(PathBuf::from(value().ok_or("--xyz needs a path")?)),
+            "--vtk" => vtk = Some(PathBuf::from(value().ok_or("--vtk needs a path")?)),
+            "--csv" => csv = Some(PathBuf::from(value().ok_or("--csv needs a path")?)),
+            "--png" => png = Some(PathBuf::from(value().ok_or("--png needs a path")?)),
+            "--svg" => svg = Some(PathBuf::from(value().ok_or("--svg needs a path")?)),
+            "--frame-size" => frame_size = Some(parse_size(flag, value())?),
+            "--frame-interval" => frame_interval = Some(parse_value(flag, value())?),
+            "--field-cells" => field_cells = parse_value(flag, value())?,
+            "--output-interval" => output_interval = parse_value(flag, value())?,
+            "--headless" => headless = true,
+            "--steps" => length = Some(RunLength::Steps(parse_value(flag, value())?)),
+            "--time" => length = Some(RunLength::Time(parse_finite(flag, value())?)),
+            "--dt" => dt = parse_finite(flag, value())?,
+            "--seed" => params.seed = parse_value(flag, value())?,
+            "--count" => params.count = Some(parse_value(flag, value())?),
+            "--radius" => params.radius = Some(parse_finite(flag, value())?),
+            "--temperature" => params.temperature = Some(parse_finite(flag, value())?),
+            "--gravity" => params.gravity = Some(parse_finite(flag, value())?),
+            "--restitution" => params.restitution = Some(parse_finite(flag, value())?),
+            "--threads" => threads = parse_value(flag, value())?,
+            _ if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+            _ if scenario.is_some() => return Err(format!("unexpected argument {}", flag)),
+            _ => scenario = Some(flag.to_string()),
+        }
+    }
+
+    if list {
+        return Ok(Command::List);
+    }
+    if benchmark {
+        return Ok(Command::Benchmark { threads });
+    }
+    if let Some(path) = replay {
+        if headless {
+            return Err("--replay needs a window, not --headless".to_string());
+        }
+        return Ok(Command::Replay(path));
+    }
+
+    let scenario = match (file, scenario) {
+        (Some(_), Some(name)) => {
+            return Err(format!(
+                "give either a scenario or --file, not both ({})",
+                name
+            ));
+        }
+        (Some(path), None) => ScenarioSource::File(path),
+        (None, name) => {
+            let name = name.as_deref().unwrap_or("brownian");
+            let scenario = find_scenario(name)
+                .ok_or_else(|| format!("unknown scenario {}, see --list", name))?;
+            ScenarioSource::Builtin(scenario)
+        }
+    };
+
+    if field_cells == 0 {
+        return Err("--field-cells must be positive".to_string());
+    }
+    if frame_interval == Some(0) {
+        return Err("--frame-interval must be positive".to_string());
+    }
+    if output_interval == 0 {
+        return Err("--output-interval must be positive".to_string());
+    }
+    if dt <= 0.0 {
+        return Err("--dt must be positive".to_string());
+    }
+    match length {
+        Some(RunLength::Steps(0)) => return Err("--steps must be positive".to_string()),
+        Some(RunLength::Time(time)) if time <= 0.0 => {
+            return Err("--time must be positive".to_string());
+        }
+        _ => {}
+    }
+    if params.count == Some(0) {
+        return Err("--count must be positive".to_string());
+    }
+    if params.radius.is_some_and(|r| r <= 0.0) {
+        return Err("--radius must be positive".to_string());
+    }
+    if params.radius.is_some_and(|r| r < MIN_RADIUS) {
+        return Err(format!(
+            "--radius must be at least {}, the boundaries are 1 to 2 units wide",
+            MIN_RADIUS
+        ));
+    }
+    if params.temperature.is_some_and(|t| t < 0.0) {
+        return Err("--temperature must not be negative".to_string());
+    }
+    if params
+        .restitution
+        .is_some_and(|e| !(0.0..=1.0).contains(&e))
+    {
+        return Err("--restitution must be between 0 and 1".to_string());
+    }
+    if length.is_some() && !headless {
+        return Err("--steps and --time need --headless".to_string());
+    }
+    if save.is_some() && !headless {
+        return Err("--save needs --headless".to_string());
+    }
+
+    Ok(Command::Run(Box::new(RunOptions {
+        scenario,
+        params,
+        dt,
+        threads,
+        headless: headless.then(|| length.unwrap_or(RunLength::Time(10.0))),
+        load,
+        save,
+        xyz,
+        vtk,
+        field_cells,
+        csv,
+        png,
+        svg,
+        frame_size,
+        frame_interval,
+        output_interval,
+    })))
+}
+
+impl RunOptions {
+    /// The scenario and the parameters that were set, one per line, with the
+    /// seed and stream of `rng`, the generator the run actually uses.
+    pub fn describe(&self, rng: &SimRng) -> String {
+        let mut lines = vec![match &self.scenario {
+            ScenarioSource::Builtin(scenario) => format!("scenario: {}", scenario.name),
+            ScenarioSource::File(path) => format!("scenario file: {}", path.display()),
+        }];
+        if let Some(path) = &self.load {
+            lines.push(format!("resumed from: {}", path.display()));
+        }
+        lines.push(format!("seed: {}", rng.seed()));
+        lines.push(format!("stream: {}", rng.stream()));
+        lines.push(format!("dt: {}", self.dt));
+        let p = &self.params;
+        let overrides = [
+            ("count", p.count.map(|x| x as f64)),
+            ("radius", p.radius),
+            ("temperature", p.temperature),
+            ("gravity", p.gravity),
+            ("restitution", p.restitution),
+        ];
+        for (name, value) in overrides {
+            if let Some(value) = value {
+                lines.push(format!("{}: {}", name, value));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Names of the overridden parameters the scenario doesn't read.
+pub fn unused_params(options: &RunOptions) -> Vec<&'static str> {
+    // scenario files set these per particle group
+    let uses = match options.scenario {
+        ScenarioSource::Builtin(scenario) => scenario.uses,
+        ScenarioSource::File(_) => &[],
+    };
+    let params = &options.params;
+    let given = [
+        ("count", params.count.is_some()),
+        ("radius", params.radius.is_some()),
+        ("temperature", params.temperature.is_some()),
+    ];
+    given
+        .into_iter()
+        .filter(|&(name, set)| set && !uses.contains(&name))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+pub fn print_scenarios() {
+    for s in SCENARIOS {
+        let uses = if s.uses.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", s.uses.join(", "))
+        };
+        println!("{:<16}{}{}", s.name, s.description, uses);
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {} for {}", value, flag))
+}
+
+/// Like `parse_value`, but rejects NaN and infinities.
+fn parse_finite(flag: &str, value: Option<String>) -> Result<f64, String> {
+    let number: f64 = parse_value(flag, value)?;
+    if !number.is_finite() {
+        return Err(format!("{} must be a finite number", flag));
+    }
+    Ok(number)
+}
+
+fn parse_size(flag: &str, value: Option<String>) -> Result<(usize, usize), String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    let size = value
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+    match size {
+        Some((w, h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(format!(
+            "invalid size {} for {}, expected e.g. 1280x720",
+            value, flag
+        )),
+    }
+}
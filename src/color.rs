@@ -0,0 +1,36 @@
+/// RGBA color with components from 0 to 1, independent of the renderer.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color { r, g, b, a }
+    }
+}
+
+// same values as the macroquad palette, so every output looks like the window
+pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+pub const RED: Color = Color::new(0.90, 0.16, 0.22, 1.0);
+pub const YELLOW: Color = Color::new(0.99, 0.98, 0.0, 1.0);
+
+// particle colors of the scenarios
+pub const GREEN: Color = Color::new(0.0, 0.8667, 0.8353, 1.0);
+pub const CRIMSON: Color = Color::new(0.9254, 0.0745, 0.2745, 1.0);
+
+/// Outline of the boundary in every output.
+pub const BOUNDARY: Color = Color::new(0.5, 0.5, 0.5, 1.0);
+/// Bars around the view when the output has another aspect ratio.
+pub const LETTERBOX: Color = Color::new(0.08, 0.08, 0.08, 1.0);
+
+#[cfg(feature = "render")]
+impl From<Color> for macroquad::color::Color {
+    fn from(c: Color) -> Self {
+        macroquad::color::Color::new(c.r, c.g, c.b, c.a)
+    }
+}
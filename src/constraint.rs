@@ -0,0 +1,136 @@
+use crate::core::ParticleId;
+use crate::core::ParticleIds;
+use crate::particle_store::ParticleStore;
+use crate::vector2::dot;
+
+const DISTANCE_ITERATIONS: usize = 8;
+
+/// Link between two particles, referred to by id so it survives reordering
+/// and removal of other particles. It is dropped with either of its particles.
+#[derive(Debug, Clone, Copy)]
+pub enum Constraint {
+    /// Hooke spring with damping along the connecting line.
+    Spring {
+        a: ParticleId,
+        b: ParticleId,
+        rest_length: f64,
+        stiffness: f64,
+        damping: f64,
+    },
+    /// Keeps the two particles at a fixed distance.
+    Distance {
+        a: ParticleId,
+        b: ParticleId,
+        length: f64,
+    },
+}
+
+impl Constraint {
+    pub fn spring(
+        a: ParticleId,
+        b: ParticleId,
+        rest_length: f64,
+        stiffness: f64,
+        damping: f64,
+    ) -> Self {
+        Constraint::Spring {
+            a,
+            b,
+            rest_length,
+            stiffness,
+            damping,
+        }
+    }
+
+    pub fn distance(a: ParticleId, b: ParticleId, length: f64) -> Self {
+        Constraint::Distance { a, b, length }
+    }
+
+    pub fn particles(&self) -> (ParticleId, ParticleId) {
+        match *self {
+            Constraint::Spring { a, b, .. } => (a, b),
+            Constraint::Distance { a, b, .. } => (a, b),
+        }
+    }
+
+    /// Current indices of the two particles, `None` if one of them is gone.
+    pub fn indices(&self, ids: &ParticleIds) -> Option<(usize, usize)> {
+        let (a, b) = self.particles();
+        Some((ids.index_of(a)?, ids.index_of(b)?))
+    }
+}
+
+/// Adds the spring forces to the velocities, to be called before integration.
+pub fn apply_spring_forces(
+    particles: &mut ParticleStore,
+    ids: &ParticleIds,
+    constraints: &[Constraint],
+    dt: f64,
+) {
+    for c in constraints {
+        let Constraint::Spring {
+            rest_length,
+            stiffness,
+            damping,
+            ..
+        } = *c
+        else {
+            continue;
+        };
+        let Some((a, b)) = c.indices(ids) else {
+            continue;
+        };
+
+        let delta = particles.positions[b] - particles.positions[a];
+        let dist = delta.length();
+        if dist == 0.0 {
+            continue;
+        }
+        let n = delta / dist;
+
+        // positive pulls the particles together
+        let relative_velocity = particles.velocities[b] - particles.velocities[a];
+        let force = stiffness * (dist - rest_length) + damping * dot(relative_velocity, n);
+
+        let (inv_a, inv_b) = (particles.inverse_mass(a), particles.inverse_mass(b));
+        particles.velocities[a] += n * (force * inv_a * dt);
+        particles.velocities[b] -= n * (force * inv_b * dt);
+    }
+}
+
+/// Projects positions and velocities of rigid links back onto their length.
+pub fn solve_distance_constraints(
+    particles: &mut ParticleStore,
+    ids: &ParticleIds,
+    constraints: &[Constraint],
+) {
+    // resolve the ids once, not in every iteration
+    let links: Vec<(usize, usize, f64)> = constraints
+        .iter()
+        .filter_map(|c| match *c {
+            Constraint::Distance { length, .. } => c.indices(ids).map(|(a, b)| (a, b, length)),
+            Constraint::Spring { .. } => None,
+        })
+        .collect();
+    for _ in 0..DISTANCE_ITERATIONS {
+        for &(a, b, length) in &links {
+            let (inv_a, inv_b) = (particles.inverse_mass(a), particles.inverse_mass(b));
+            let inv_mass_sum = inv_a + inv_b;
+            let delta = particles.positions[b] - particles.positions[a];
+            let dist = delta.length();
+            if inv_mass_sum == 0.0 || dist == 0.0 {
+                continue;
+            }
+            let n = delta / dist;
+
+            // remove the relative velocity along the link
+            let impulse = dot(particles.velocities[b] - particles.velocities[a], n) / inv_mass_sum;
+            particles.velocities[a] += n * (impulse * inv_a);
+            particles.velocities[b] -= n * (impulse * inv_b);
+
+            let correction = (dist - length) / inv_mass_sum;
+            particles.positions[a] += n * (correction * inv_a);
+            particles.positions[b] -= n * (correction * inv_b);
+        }
+    }
+}
@@ -1,14 +1,83 @@
-use macroquad::color::Color;
+use std::collections::HashMap;
 
+use crate::color::Color;
 use crate::vector2::Vector2;
+use crate::vector2::dot;
+
+/// Persistent handle of a particle, stays the same while indices change.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ParticleId(pub u64);
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Particle {
+    pub id: ParticleId,
     pub mass: f64,
     pub position: Vector2,
     pub velocity: Vector2,
     pub radius: f64,
     pub color: Color,
+    pub asleep: bool,
+    pub sleep_time: f64,        // how long the particle has been resting
+    pub cluster: Option<usize>, // index of the rigid cluster the particle belongs to
+}
+
+/// Hands out particle ids and maps them to the current indices.
+///
+/// Numbering starts at 1, so the default id 0 of a particle that wasn't added
+/// to a simulation never refers to one that was.
+#[derive(Debug, Clone)]
+pub struct ParticleIds {
+    next: u64,
+    indices: HashMap<ParticleId, usize>,
+}
+
+impl Default for ParticleIds {
+    fn default() -> Self {
+        ParticleIds {
+            next: 1,
+            indices: HashMap::new(),
+        }
+    }
+}
+
+impl ParticleIds {
+    /// Continues numbering after `next - 1`, e.g. when restoring a snapshot.
+    pub fn restore(next: u64, ids: &[ParticleId]) -> ParticleIds {
+        let mut particle_ids = ParticleIds {
+            next,
+            indices: HashMap::new(),
+        };
+        particle_ids.rebuild(ids);
+        particle_ids
+    }
+
+    /// The id the next particle will get.
+    pub fn next(&self) -> ParticleId {
+        ParticleId(self.next)
+    }
+
+    pub fn allocate(&mut self, index: usize) -> ParticleId {
+        let id = ParticleId(self.next);
+        self.next += 1;
+        self.indices.insert(id, index);
+        id
+    }
+
+    pub fn index_of(&self, id: ParticleId) -> Option<usize> {
+        self.indices.get(&id).copied()
+    }
+
+    pub fn contains(&self, id: ParticleId) -> bool {
+        self.indices.contains_key(&id)
+    }
+
+    /// Rebuilds the map after particles were removed or reordered.
+    pub fn rebuild(&mut self, ids: &[ParticleId]) {
+        self.indices.clear();
+        for (index, &id) in ids.iter().enumerate() {
+            self.indices.insert(id, index);
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -43,9 +112,22 @@ pub struct ParticleCollision {
     pub velocity2: Vector2,
 }
 
+impl ParticleCollision {
+    /// Speed at which the two particles approach each other, negative if separating.
+    pub fn approach_speed(&self) -> f64 {
+        dot(self.velocity2 - self.velocity1, self.normal)
+    }
+}
+
 pub struct StaticCollision {
     pub index: usize,
     pub normal: Vector2,
     pub penetration: f64,
     pub velocity: Vector2,
 }
+
+impl StaticCollision {
+    pub fn approach_speed(&self) -> f64 {
+        -dot(self.normal, self.velocity)
+    }
+}
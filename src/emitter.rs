@@ -0,0 +1,152 @@
+use rand::Rng;
+use rand_distr::Distribution;
+use rand_distr::Normal;
+
+use crate::color::Color;
+use crate::core::{Particle, Rectangle};
+use crate::particle_store::ParticleStore;
+use crate::rng::SimRng;
+use crate::vector2::Vector2;
+
+#[derive(Debug, Clone, Copy)]
+pub enum SpeedDistribution {
+    Constant(f64),
+    Uniform { min: f64, max: f64 },
+    Gaussian(Normal<f64>), // built with `gaussian`
+}
+
+impl SpeedDistribution {
+    /// Normally distributed speeds, fails unless the mean is finite and the
+    /// standard deviation finite and not negative.
+    pub fn gaussian(mean: f64, std_dev: f64) -> Result<SpeedDistribution, String> {
+        if !mean.is_finite() {
+            return Err(format!("speed mean {} is not finite", mean));
+        }
+        if !std_dev.is_finite() || std_dev < 0.0 {
+            return Err(format!(
+                "speed standard deviation {} must be finite and not negative",
+                std_dev
+            ));
+        }
+        Normal::new(mean, std_dev)
+            .map(SpeedDistribution::Gaussian)
+            .map_err(|err| err.to_string())
+    }
+
+    fn sample(self, rng: &mut SimRng) -> f64 {
+        match self {
+            SpeedDistribution::Constant(speed) => speed,
+            SpeedDistribution::Uniform { min, max } if min < max => rng.random_range(min..max),
+            SpeedDistribution::Uniform { min, .. } => min,
+            SpeedDistribution::Gaussian(normal) => normal.sample(rng),
+        }
+    }
+}
+
+/// Spawns new particles at a fixed rate.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub position: Vector2,
+    pub direction: Vector2,
+    pub spread: f64, // half opening angle of the emission cone in radians
+    pub rate: f64,   // particles per second
+    pub speed: SpeedDistribution,
+    pub radius: f64,
+    pub mass: f64,
+    pub color: Color,
+    pending: f64,
+}
+
+impl Emitter {
+    pub fn new(
+        position: Vector2,
+        direction: Vector2,
+        rate: f64,
+        speed: SpeedDistribution,
+        radius: f64,
+        color: Color,
+    ) -> Emitter {
+        Emitter {
+            position,
+            direction: direction.normalized(),
+            spread: 0.0,
+            rate,
+            speed,
+            radius,
+            mass: std::f64::consts::PI * radius * radius,
+            color,
+            pending: 0.0,
+        }
+    }
+
+    pub fn with_spread(mut self, spread: f64) -> Emitter {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f64) -> Emitter {
+        self.mass = mass;
+        self
+    }
+
+    /// Fraction of a particle that has built up towards the next emission.
+    pub fn pending(&self) -> f64 {
+        self.pending
+    }
+
+    pub fn set_pending(&mut self, pending: f64) {
+        self.pending = pending;
+    }
+
+    /// Returns the particles emitted during `dt`. Emission is held back while
+    /// the nozzle is blocked by another particle.
+    pub fn emit(&mut self, dt: f64, particles: &ParticleStore, rng: &mut SimRng) -> Vec<Particle> {
+        self.pending += self.rate * dt;
+
+        let mut emitted: Vec<Particle> = Vec::new();
+        while self.pending >= 1.0 {
+            let overlaps = |position: Vector2, radius: f64| {
+                (position - self.position).length_squared() < (radius + self.radius).powi(2)
+            };
+            let blocked = particles
+                .positions
+                .iter()
+                .zip(&particles.radii)
+                .any(|(&position, &radius)| overlaps(position, radius))
+                || emitted.iter().any(|p| overlaps(p.position, p.radius));
+            if blocked {
+                // don't build up a burst while waiting
+                self.pending = self.pending.min(1.0);
+                break;
+            }
+
+            let angle = if self.spread > 0.0 {
+                rng.random_range(-self.spread..self.spread)
+            } else {
+                0.0
+            };
+            emitted.push(Particle {
+                mass: self.mass,
+                position: self.position,
+                velocity: self.direction.rotated(angle) * self.speed.sample(rng),
+                radius: self.radius,
+                color: self.color,
+                ..Default::default()
+            });
+            self.pending -= 1.0;
+        }
+        emitted
+    }
+}
+
+/// Removes every particle whose center enters the region.
+#[derive(Debug, Clone, Copy)]
+pub struct Sink {
+    pub region: Rectangle,
+}
+
+impl Sink {
+    pub fn absorbs(&self, position: Vector2) -> bool {
+        self.region.contains(position)
+    }
+}
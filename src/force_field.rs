@@ -0,0 +1,112 @@
+use crate::core::Rectangle;
+use crate::vector2::Vector2;
+
+/// External force acting on every awake particle, in addition to gravity.
+///
+/// Closures taking `(position, velocity, time)` are force fields as well.
+pub trait ForceField: Send + Sync {
+    fn force(&self, position: Vector2, velocity: Vector2, time: f64) -> Vector2;
+}
+
+impl<F> ForceField for F
+where
+    F: Fn(Vector2, Vector2, f64) -> Vector2 + Send + Sync,
+{
+    fn force(&self, position: Vector2, velocity: Vector2, time: f64) -> Vector2 {
+        self(position, velocity, time)
+    }
+}
+
+/// Pulls particles towards a point, a negative strength pushes them away.
+#[derive(Debug, Clone, Copy)]
+pub struct RadialAttractor {
+    pub center: Vector2,
+    pub strength: f64,
+    pub softening: f64, // keeps the force finite close to the center
+}
+
+impl ForceField for RadialAttractor {
+    fn force(&self, position: Vector2, _velocity: Vector2, _time: f64) -> Vector2 {
+        let r = self.center - position;
+        let d2 = r.length_squared() + self.softening * self.softening;
+        r * (self.strength / (d2 * d2.sqrt()))
+    }
+}
+
+/// Swirls particles counter-clockwise around a point, clockwise for a negative strength.
+#[derive(Debug, Clone, Copy)]
+pub struct Vortex {
+    pub center: Vector2,
+    pub strength: f64,
+    pub core_radius: f64, // inside the core the force falls off to zero
+}
+
+impl ForceField for Vortex {
+    fn force(&self, position: Vector2, _velocity: Vector2, _time: f64) -> Vector2 {
+        let r = position - self.center;
+        r.perp() * (self.strength / (r.length_squared() + self.core_radius * self.core_radius))
+    }
+}
+
+/// Friction with a medium at rest, proportional to the velocity.
+#[derive(Debug, Clone, Copy)]
+pub struct LinearDrag {
+    pub coefficient: f64,
+}
+
+impl ForceField for LinearDrag {
+    fn force(&self, _position: Vector2, velocity: Vector2, _time: f64) -> Vector2 {
+        velocity * -self.coefficient
+    }
+}
+
+/// Drags particles inside the region towards the wind velocity.
+#[derive(Debug, Clone, Copy)]
+pub struct WindZone {
+    pub region: Rectangle,
+    pub velocity: Vector2,
+    pub coefficient: f64,
+}
+
+impl ForceField for WindZone {
+    fn force(&self, position: Vector2, velocity: Vector2, _time: f64) -> Vector2 {
+        if self.region.contains(position) {
+            (self.velocity - velocity) * self.coefficient
+        } else {
+            Vector2::ZERO
+        }
+    }
+}
+
+/// Scales another field by a function of time.
+pub struct TimeVarying {
+    pub field: Box<dyn ForceField>,
+    pub scale: Box<dyn Fn(f64) -> f64 + Send + Sync>,
+}
+
+impl TimeVarying {
+    pub fn new<F, S>(field: F, scale: S) -> TimeVarying
+    where
+        F: ForceField + 'static,
+        S: Fn(f64) -> f64 + Send + Sync + 'static,
+    {
+        TimeVarying {
+            field: Box::new(field),
+            scale: Box::new(scale),
+        }
+    }
+
+    /// Switches the field direction back and forth with a sine of the given period.
+    pub fn oscillating<F>(field: F, period: f64) -> TimeVarying
+    where
+        F: ForceField + 'static,
+    {
+        TimeVarying::new(field, move |t| (std::f64::consts::TAU * t / period).sin())
+    }
+}
+
+impl ForceField for TimeVarying {
+    fn force(&self, position: Vector2, velocity: Vector2, time: f64) -> Vector2 {
+        self.field.force(position, velocity, time) * (self.scale)(time)
+    }
+}
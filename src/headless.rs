@@ -0,0 +1,201 @@
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::simulation::Simulation;
+
+/// Receives the simulation state during a headless run, e.g. to write output files.
+pub trait Observer {
+    /// Called before the first step and then every `interval` steps.
+    fn observe(&mut self, sim: &Simulation) -> io::Result<()>;
+
+    /// Steps between calls, `None` uses the runner's `output_interval`.
+    fn interval(&self) -> Option<u64> {
+        None
+    }
+
+    /// Called once after the last step.
+    fn finish(&mut self, _sim: &Simulation) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RunLength {
+    Steps(u64),
+    Time(f64), // simulated seconds
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessOptions {
+    pub dt: f64,
+    pub length: RunLength,
+    pub output_interval: u64, // steps between calls to observers without their own interval
+    pub progress_interval: f64, // wall clock seconds between progress lines, 0 for none
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        HeadlessOptions {
+            dt: 0.001,
+            length: RunLength::Time(1.0),
+            output_interval: 100,
+            progress_interval: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RunSummary {
+    pub steps: u64,
+    pub simulated_time: f64,
+    pub wall_time: f64,
+}
+
+/// Steps the simulation without a window, printing progress to stderr.
+pub fn run_headless(
+    sim: &mut Simulation,
+    options: &HeadlessOptions,
+    observers: &mut [Box<dyn Observer>],
+) -> io::Result<RunSummary> {
+    let total_steps = match options.length {
+        RunLength::Steps(steps) => steps,
+        RunLength::Time(time) => (time / options.dt).round() as u64,
+    };
+    let output_interval = options.output_interval;
+
+    let start = Instant::now();
+    let mut last_progress = 0.0;
+    for observer in observers.iter_mut() {
+        observer.observe(sim)?;
+    }
+
+    for step in 1..=total_steps {
+        sim.update(options.dt);
+
+        observe_due(observers, sim, step, output_interval)?;
+
+        let elapsed = start.elapsed().as_secs_f64();
+        if options.progress_interval > 0.0 && elapsed - last_progress >= options.progress_interval {
+            last_progress = elapsed;
+            print_progress(sim, step, total_steps, elapsed);
+        }
+    }
+
+    for observer in observers.iter_mut() {
+        observer.finish(sim)?;
+    }
+
+    let summary = RunSummary {
+        steps: total_steps,
+        simulated_time: total_steps as f64 * options.dt,
+        wall_time: start.elapsed().as_secs_f64(),
+    };
+    if options.progress_interval > 0.0 {
+        print_progress(sim, total_steps, total_steps, summary.wall_time);
+    }
+    Ok(summary)
+}
+
+/// Calls the observers whose interval divides `step`, the number of steps
+/// since the run started.
+pub fn observe_due(
+    observers: &mut [Box<dyn Observer>],
+    sim: &Simulation,
+    step: u64,
+    output_interval: u64,
+) -> io::Result<()> {
+    for observer in observers.iter_mut() {
+        let interval = observer.interval().unwrap_or(output_interval).max(1);
+        if step.is_multiple_of(interval) {
+            observer.observe(sim)?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits `out/run` into the directory `out`, which is created, and the stem
+/// `run` for writers that number their files.
+pub fn output_base(base: &Path) -> io::Result<(PathBuf, String)> {
+    let stem = base
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "missing file name"))?;
+    let directory = base.parent().unwrap_or(Path::new("")).to_path_buf();
+    if !directory.as_os_str().is_empty() {
+        std::fs::create_dir_all(&directory)?;
+    }
+    Ok((directory, stem.to_string()))
+}
+
+/// Writes one image of the simulation for a `FrameSequence`.
+pub trait FrameWriter {
+    /// File extension of the frames, without the dot.
+    const EXTENSION: &'static str;
+
+    fn write(&mut self, path: &Path, sim: &Simulation) -> io::Result<()>;
+}
+
+/// Writes numbered frames `base_00000.ext`, `base_00001.ext`, ...
+pub struct FrameSequence<W> {
+    directory: PathBuf,
+    stem: String,
+    interval: Option<u64>,
+    writer: W,
+    frame: usize,
+}
+
+impl<W: FrameWriter> FrameSequence<W> {
+    pub fn new(base: &Path, writer: W) -> io::Result<FrameSequence<W>> {
+        let (directory, stem) = output_base(base)?;
+        Ok(FrameSequence {
+            directory,
+            stem,
+            interval: None,
+            writer,
+            frame: 0,
+        })
+    }
+
+    /// Steps between frames instead of the runner's output interval.
+    pub fn with_interval(mut self, steps: u64) -> FrameSequence<W> {
+        self.interval = Some(steps);
+        self
+    }
+
+    pub fn write_frame(&mut self, sim: &Simulation) -> io::Result<()> {
+        let path = self
+            .directory
+            .join(format!("{}_{:05}.{}", self.stem, self.frame, W::EXTENSION));
+        self.frame += 1;
+        self.writer.write(&path, sim)
+    }
+}
+
+impl<W: FrameWriter> Observer for FrameSequence<W> {
+    fn observe(&mut self, sim: &Simulation) -> io::Result<()> {
+        self.write_frame(sim)
+    }
+
+    fn interval(&self) -> Option<u64> {
+        self.interval
+    }
+}
+
+fn print_progress(sim: &Simulation, step: u64, total_steps: u64, elapsed: f64) {
+    let percent = if total_steps > 0 {
+        100.0 * step as f64 / total_steps as f64
+    } else {
+        100.0
+    };
+    eprintln!(
+        "step {}/{} ({:.1}%), t = {:.4}, {} particles, {:.1} s",
+        step,
+        total_steps,
+        percent,
+        sim.time,
+        sim.particles.len(),
+        elapsed
+    );
+}
@@ -0,0 +1,247 @@
+use std::f64::consts::PI;
+
+use macroquad::color::GRAY;
+use macroquad::color::WHITE;
+use macroquad::input::KeyCode;
+use macroquad::input::MouseButton;
+use macroquad::input::is_key_pressed;
+use macroquad::input::is_mouse_button_down;
+use macroquad::input::is_mouse_button_pressed;
+use macroquad::input::is_mouse_button_released;
+use macroquad::input::mouse_position;
+use macroquad::shapes::draw_circle_lines;
+use macroquad::shapes::draw_line;
+
+use crate::color::GREEN;
+use crate::core::Particle;
+use crate::core::ParticleId;
+use crate::simulation::Simulation;
+use crate::sleep;
+use crate::vector2::Vector2;
+use crate::viewport::Viewport;
+
+const GRAB_FREQUENCY: f64 = 20.0; // of the critically damped grab spring, in 1/s
+const BRUSH_ACCELERATION: f64 = 20.0; // at the brush center, in view heights per s^2
+const SPAWN_VELOCITY: f64 = 2.0; // per unit of drag length, in 1/s
+const PICK_TOLERANCE: f64 = 4.0; // pixels around small particles that still grab them
+const SIZE_STEP: f64 = 1.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Grab,
+    Push,
+    Pull,
+    Spawn,
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Grab => "grab",
+            Tool::Push => "push",
+            Tool::Pull => "pull",
+            Tool::Spawn => "spawn",
+        }
+    }
+}
+
+/// Mouse tools of the window, the left button uses the selected one.
+///
+/// Grab pulls the particle under the cursor along with a spring, push and
+/// pull accelerate the particles under the brush away from the cursor or
+/// towards it, spawn adds a particle where the button goes down with a
+/// velocity from the drag until it is released.
+pub struct MouseTools {
+    pub tool: Tool,
+    pub brush_radius: f64, // in simulation units
+    pub spawn_radius: f64,
+    cursor: Vector2, // mouse position in simulation units
+    grabbed: Option<ParticleId>,
+    brushing: bool,
+    spawn_start: Option<Vector2>,
+}
+
+impl MouseTools {
+    pub fn new(sim: &Simulation) -> MouseTools {
+        let p = &sim.particles;
+        let spawn_radius = if p.is_empty() {
+            0.01 * sim.view.height()
+        } else {
+            p.radii.iter().sum::<f64>() / p.len() as f64
+        };
+        MouseTools {
+            tool: Tool::Grab,
+            brush_radius: 0.05 * sim.view.height(),
+            spawn_radius,
+            cursor: Vector2::ZERO,
+            grabbed: None,
+            brushing: false,
+            spawn_start: None,
+        }
+    }
+
+    /// Reads the keys and the mouse, once per frame. 1 to 4 select the tool,
+    /// [ and ] shrink and grow the brush or the spawned particles.
+    pub fn handle_input(&mut self, sim: &mut Simulation, viewport: Viewport) {
+        let keys = [
+            (KeyCode::Key1, Tool::Grab),
+            (KeyCode::Key2, Tool::Push),
+            (KeyCode::Key3, Tool::Pull),
+            (KeyCode::Key4, Tool::Spawn),
+        ];
+        for (key, tool) in keys {
+            if is_key_pressed(key) && self.tool != tool {
+                self.tool = tool;
+                self.grabbed = None;
+                self.brushing = false;
+                self.spawn_start = None;
+            }
+        }
+        let size = match self.tool {
+            Tool::Spawn => &mut self.spawn_radius,
+            _ => &mut self.brush_radius,
+        };
+        if is_key_pressed(KeyCode::RightBracket) {
+            *size *= SIZE_STEP;
+        }
+        if is_key_pressed(KeyCode::LeftBracket) {
+            *size /= SIZE_STEP;
+        }
+
+        let (x, y) = mouse_position();
+        self.cursor = viewport.to_world(Vector2::new(x as f64, y as f64));
+        let down = is_mouse_button_down(MouseButton::Left);
+        let pressed = is_mouse_button_pressed(MouseButton::Left);
+        match self.tool {
+            Tool::Grab => {
+                if pressed {
+                    let tolerance = PICK_TOLERANCE / viewport.scale().x;
+                    self.grabbed = particle_at(sim, self.cursor, tolerance);
+                }
+                if !down {
+                    self.grabbed = None;
+                }
+            }
+            Tool::Push | Tool::Pull => self.brushing = down,
+            Tool::Spawn => {
+                if pressed {
+                    self.spawn_start = Some(self.cursor);
+                }
+                if is_mouse_button_released(MouseButton::Left)
+                    && let Some(start) = self.spawn_start.take()
+                {
+                    self.spawn(sim, start, SPAWN_VELOCITY * (self.cursor - start));
+                }
+            }
+        }
+    }
+
+    /// Accelerates the grabbed and brushed particles, before every step.
+    pub fn apply(&self, sim: &mut Simulation, dt: f64) {
+        if let Some(index) = self.grabbed.and_then(|id| sim.index_of(id)) {
+            let p = &mut sim.particles;
+            let w = GRAB_FREQUENCY;
+            let acceleration =
+                w * w * (self.cursor - p.positions[index]) - 2.0 * w * p.velocities[index];
+            p.velocities[index] += dt * acceleration;
+            sleep::wake(p, index);
+        }
+
+        if self.brushing {
+            let sign = if self.tool == Tool::Push { 1.0 } else { -1.0 };
+            let strength = sign * BRUSH_ACCELERATION * sim.view.height();
+            let p = &mut sim.particles;
+            for i in 0..p.len() {
+                let offset = p.positions[i] - self.cursor;
+                let distance = offset.length();
+                if distance > 0.0 && distance < self.brush_radius {
+                    let falloff = 1.0 - distance / self.brush_radius;
+                    p.velocities[i] += dt * strength * falloff * offset / distance;
+                    sleep::wake(p, i);
+                }
+            }
+        }
+    }
+
+    /// Adds a particle unless it would overlap another one or the walls.
+    fn spawn(&self, sim: &mut Simulation, position: Vector2, velocity: Vector2) {
+        let r = self.spawn_radius;
+        let b = sim.boundary;
+        let inside = position.x - r >= b.min.x
+            && position.x + r <= b.max.x
+            && position.y - r >= b.min.y
+            && position.y + r <= b.max.y;
+        let p = &sim.particles;
+        let free = (0..p.len()).all(|i| (p.positions[i] - position).length() >= r + p.radii[i]);
+        if inside && free {
+            sim.add_particle(Particle {
+                position,
+                velocity,
+                radius: r,
+                mass: PI * r * r,
+                color: GREEN,
+                ..Default::default()
+            });
+        }
+    }
+
+    /// Draws the brush, the particle to spawn or the grab spring.
+    pub fn draw(&self, sim: &Simulation, viewport: Viewport) {
+        let scale = viewport.scale().x;
+        let cursor = viewport.to_screen(self.cursor);
+        let line = |a: Vector2, b: Vector2| {
+            draw_line(a.x as f32, a.y as f32, b.x as f32, b.y as f32, 1.0, WHITE)
+        };
+        let circle = |center: Vector2, radius: f64| {
+            draw_circle_lines(
+                center.x as f32,
+                center.y as f32,
+                (scale * radius) as f32,
+                1.0,
+                GRAY,
+            )
+        };
+        match self.tool {
+            Tool::Grab => {
+                if let Some(index) = self.grabbed.and_then(|id| sim.index_of(id)) {
+                    line(viewport.to_screen(sim.particles.positions[index]), cursor);
+                }
+            }
+            Tool::Push | Tool::Pull => circle(cursor, self.brush_radius),
+            Tool::Spawn => match self.spawn_start {
+                Some(start) => {
+                    let start = viewport.to_screen(start);
+                    circle(start, self.spawn_radius);
+                    line(start, cursor);
+                }
+                None => circle(cursor, self.spawn_radius),
+            },
+        }
+    }
+
+    /// One line for the HUD.
+    pub fn status(&self) -> String {
+        match self.tool {
+            Tool::Spawn => format!("Tool: spawn, radius {:.4}", self.spawn_radius),
+            Tool::Push | Tool::Pull => {
+                format!(
+                    "Tool: {}, radius {:.4}",
+                    self.tool.name(),
+                    self.brush_radius
+                )
+            }
+            Tool::Grab => "Tool: grab".to_string(),
+        }
+    }
+}
+
+/// The particle under `point` with the closest center, small particles are
+/// picked from up to `tolerance` away.
+pub fn particle_at(sim: &Simulation, point: Vector2, tolerance: f64) -> Option<ParticleId> {
+    let p = &sim.particles;
+    let distance = |i: usize| (p.positions[i] - point).length();
+    (0..p.len())
+        .filter(|&i| distance(i) <= p.radii[i].max(tolerance))
+        .min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+        .map(|i| p.ids[i])
+}
@@ -0,0 +1,40 @@
+//! A 2D particle simulation with rigid body collisions, constraints, emitters
+//! and force fields, plus writers for snapshots, trajectories and images.
+//!
+//! The physics and the file outputs have no windowing dependencies. The
+//! `render` feature, on by default, adds the macroquad window and the replay
+//! viewer.
+
+pub mod benchmark;
+#[cfg(feature = "render")]
+pub mod camera;
+pub mod color;
+pub mod constraint;
+pub mod core;
+pub mod emitter;
+pub mod force_field;
+pub mod headless;
+#[cfg(feature = "render")]
+pub mod interaction;
+pub mod observables;
+pub mod parallel;
+pub mod particle_store;
+pub mod raster;
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "render")]
+pub mod replay;
+pub mod rigid_cluster;
+pub mod rng;
+pub mod scenario_file;
+pub mod simulation;
+pub mod simulation_factory;
+pub mod sleep;
+pub mod snapshot;
+pub mod spatial_order;
+pub mod svg;
+pub mod uniform_grid;
+pub mod vector2;
+pub mod viewport;
+pub mod vtk;
+pub mod xyz;
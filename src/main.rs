@@ -1,17 +1,147 @@
-mod core;
-mod render;
-mod simulation;
-mod simulation_factory;
-mod vector2;
+mod cli;
 
 use macroquad::prelude::*;
+use simple_fluid_simulation::benchmark;
+use simple_fluid_simulation::headless::HeadlessOptions;
+use simple_fluid_simulation::headless::Observer;
+use simple_fluid_simulation::headless::run_headless;
+use simple_fluid_simulation::observables::CsvRecorder;
+use simple_fluid_simulation::raster::PngSequence;
+use simple_fluid_simulation::render::run;
+use simple_fluid_simulation::replay::run_replay;
+use simple_fluid_simulation::scenario_file;
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::snapshot::Snapshot;
+use simple_fluid_simulation::svg::SvgSequence;
+use simple_fluid_simulation::viewport::default_size;
+use simple_fluid_simulation::vtk::VtkWriter;
+use simple_fluid_simulation::xyz::Trajectory;
+use simple_fluid_simulation::xyz::XyzWriter;
 
-use crate::render::run;
+use crate::cli::Command;
+use crate::cli::RunOptions;
+use crate::cli::ScenarioSource;
 
-#[macroquad::main("Simulation")]
-async fn main() {
-    let mut sim = simulation_factory::brownian_motion_sim();
-    request_new_screen_size(sim.window_width, sim.window_height);
-    let fixed_dt = 0.001;
-    run(&mut sim, fixed_dt).await;
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Help => print!("{}", cli::USAGE),
+        Command::List => cli::print_scenarios(),
+        Command::Benchmark { threads } => println!("{}", benchmark::brownian_motion(5000, threads)),
+        Command::Run(options) => run_scenario(*options),
+        Command::Replay(path) => {
+            let trajectory = or_exit(Trajectory::load(&path).map_err(|err| in_file(&path, err)));
+            macroquad::Window::new("Replay", replay_window(trajectory));
+        }
+    }
+}
+
+fn run_scenario(options: RunOptions) {
+    for name in cli::unused_params(&options) {
+        eprintln!("Warning: {} is not used by this scenario", name);
+    }
+
+    let mut sim = match &options.scenario {
+        ScenarioSource::Builtin(scenario) => or_exit(scenario.build(&options.params)),
+        ScenarioSource::File(path) => or_exit(scenario_file::load(path, &options.params)),
+    };
+    sim.threads = options.threads;
+
+    if let Some(path) = &options.load {
+        let snapshot = or_exit(Snapshot::load(path).map_err(|err| in_file(path, err)));
+        or_exit(snapshot.apply(&mut sim).map_err(|err| in_file(path, err)));
+    }
+
+    let mut observers: Vec<Box<dyn Observer>> = Vec::new();
+    if let Some(path) = &options.xyz {
+        let writer = XyzWriter::create(path).map_err(|err| in_file(path, err));
+        observers.push(Box::new(or_exit(writer)));
+    }
+    if let Some(path) = &options.csv {
+        let recorder = CsvRecorder::create(path, &options.describe(&sim.rng));
+        observers.push(Box::new(or_exit(
+            recorder.map_err(|err| in_file(path, err)),
+        )));
+    }
+    let (width, height) = options.frame_size.unwrap_or(default_size(sim.view));
+    if let Some(path) = &options.png {
+        let frames =
+            or_exit(PngSequence::create(path, width, height).map_err(|err| in_file(path, err)));
+        observers.push(match options.frame_interval {
+            Some(interval) => Box::new(frames.with_interval(interval)),
+            None => Box::new(frames),
+        });
+    }
+    if let Some(path) = &options.svg {
+        let frames =
+            or_exit(SvgSequence::create(path, width, height).map_err(|err| in_file(path, err)));
+        observers.push(match options.frame_interval {
+            Some(interval) => Box::new(frames.with_interval(interval)),
+            None => Box::new(frames),
+        });
+    }
+    if let Some(path) = &options.vtk {
+        let writer = VtkWriter::create(path).map_err(|err| in_file(path, err));
+        observers.push(Box::new(
+            or_exit(writer).with_field_resolution(options.field_cells),
+        ));
+    }
+
+    match options.headless {
+        Some(length) => {
+            let headless_options = HeadlessOptions {
+                dt: options.dt,
+                length,
+                output_interval: options.output_interval,
+                ..Default::default()
+            };
+            or_exit(run_headless(&mut sim, &headless_options, &mut observers));
+            if let Some(path) = &options.save {
+                or_exit(
+                    Snapshot::capture(&sim)
+                        .save(path)
+                        .map_err(|err| in_file(path, err)),
+                );
+            }
+        }
+        None => macroquad::Window::new(
+            "Simulation",
+            run_window(sim, options.dt, observers, options.output_interval),
+        ),
+    }
+}
+
+/// Unwraps the result or prints the error and exits.
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    })
+}
+
+fn in_file(path: &std::path::Path, err: std::io::Error) -> String {
+    format!("{}: {}", path.display(), err)
+}
+
+async fn run_window(
+    mut sim: Simulation,
+    fixed_dt: f64,
+    observers: Vec<Box<dyn Observer>>,
+    output_interval: u64,
+) {
+    let (width, height) = default_size(sim.view);
+    request_new_screen_size(width as f32, height as f32);
+    run(&mut sim, fixed_dt, observers, output_interval).await;
+}
+
+async fn replay_window(trajectory: Trajectory) {
+    run_replay(&trajectory).await;
 }
@@ -0,0 +1,150 @@
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+use crate::core::ParticleId;
+use crate::headless::Observer;
+use crate::particle_store::ParticleStore;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+pub fn kinetic_energy(particles: &ParticleStore) -> f64 {
+    particles
+        .masses
+        .iter()
+        .zip(&particles.velocities)
+        .map(|(&m, v)| 0.5 * m * v.length_squared())
+        .sum()
+}
+
+pub fn momentum(particles: &ParticleStore) -> Vector2 {
+    particles
+        .masses
+        .iter()
+        .zip(&particles.velocities)
+        .fold(Vector2::ZERO, |acc, (&m, &v)| acc + m * v)
+}
+
+/// Kinetic temperature with k_B = 1: the kinetic energy per particle relative
+/// to the center of mass motion, each particle has two degrees of freedom.
+pub fn temperature(particles: &ParticleStore) -> f64 {
+    let total_mass: f64 = particles.masses.iter().sum();
+    if particles.is_empty() || total_mass <= 0.0 {
+        return 0.0;
+    }
+    let drift = momentum(particles) / total_mass;
+    let thermal: f64 = particles
+        .masses
+        .iter()
+        .zip(&particles.velocities)
+        .map(|(&m, &v)| 0.5 * m * (v - drift).length_squared())
+        .sum();
+    thermal / particles.len() as f64
+}
+
+/// Samples observables into a CSV file, one row per call.
+///
+/// The pressure is the impulse the walls took up since the previous row per
+/// time and wall length, so the first row leaves it empty. Tracked particles
+/// get a pair of position columns each, empty once the particle is gone.
+pub struct CsvRecorder<W: Write> {
+    out: W,
+    description: String,
+    tracked: Option<Vec<ParticleId>>, // fixed with the header at the first row
+    last_sample: Option<(f64, f64)>,  // time and wall impulse
+}
+
+impl CsvRecorder<BufWriter<File>> {
+    pub fn create(path: &Path, description: &str) -> io::Result<Self> {
+        Ok(CsvRecorder::new(
+            BufWriter::new(File::create(path)?),
+            description,
+        ))
+    }
+}
+
+impl<W: Write> CsvRecorder<W> {
+    /// `description` goes into the header, e.g. the scenario and its parameters.
+    pub fn new(out: W, description: &str) -> Self {
+        CsvRecorder {
+            out,
+            description: description.to_string(),
+            tracked: None,
+            last_sample: None,
+        }
+    }
+
+    fn write_header(&mut self, sim: &Simulation) -> io::Result<()> {
+        let mut tracked: Vec<ParticleId> = sim.trails.keys().copied().collect();
+        tracked.sort();
+
+        writeln!(self.out, "# simple_fluids observables")?;
+        for line in self.description.lines() {
+            writeln!(self.out, "# {}", line)?;
+        }
+        writeln!(
+            self.out,
+            "# units: simulation units with k_B = 1, energy in mass length^2/time^2, \
+             pressure as force per wall length"
+        )?;
+        write!(
+            self.out,
+            "time,step,particles,kinetic_energy,momentum_x,momentum_y,temperature,pressure"
+        )?;
+        for id in &tracked {
+            write!(self.out, ",x_{},y_{}", id.0, id.0)?;
+        }
+        writeln!(self.out)?;
+
+        self.tracked = Some(tracked);
+        Ok(())
+    }
+
+    pub fn write_row(&mut self, sim: &Simulation) -> io::Result<()> {
+        if self.tracked.is_none() {
+            self.write_header(sim)?;
+        }
+
+        let p = &sim.particles;
+        let momentum = momentum(p);
+        write!(
+            self.out,
+            "{},{},{},{},{},{},{},",
+            sim.time,
+            sim.steps,
+            p.len(),
+            kinetic_energy(p),
+            momentum.x,
+            momentum.y,
+            temperature(p)
+        )?;
+
+        if let Some((time, impulse)) = self.last_sample {
+            let perimeter = 2.0 * (sim.boundary.width() + sim.boundary.height());
+            if sim.time > time {
+                let pressure = (sim.wall_impulse - impulse) / ((sim.time - time) * perimeter);
+                write!(self.out, "{}", pressure)?;
+            }
+        }
+        self.last_sample = Some((sim.time, sim.wall_impulse));
+
+        for &id in self.tracked.iter().flatten() {
+            match sim.particle(id) {
+                Some(particle) => {
+                    write!(self.out, ",{},{}", particle.position.x, particle.position.y)?
+                }
+                None => write!(self.out, ",,")?,
+            }
+        }
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Observer for CsvRecorder<W> {
+    fn observe(&mut self, sim: &Simulation) -> io::Result<()> {
+        self.write_row(sim)
+    }
+}
@@ -0,0 +1,71 @@
+use std::ops::Range;
+use std::thread;
+
+/// Splits `0..len` into one contiguous range per thread, runs `f` on every
+/// range and returns the results in range order. The split only depends on
+/// `threads` and `len`, so results that are combined in order don't depend on
+/// the scheduling. Runs on the calling thread for `threads <= 1`.
+pub fn map_ranges<T, F>(threads: usize, len: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(Range<usize>) -> T + Sync,
+{
+    if threads <= 1 || len < 2 {
+        return vec![f(0..len)];
+    }
+
+    let ranges = split(threads, len);
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = ranges
+            .into_iter()
+            .map(|range| scope.spawn(move || f(range)))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// Runs `f` on matching contiguous chunks of `a` and `b`, one pair per thread,
+/// and returns its results in chunk order. `f` also gets the index of the
+/// first item of its chunks.
+pub fn map_chunks_mut<A, B, T, F>(threads: usize, a: &mut [A], b: &mut [B], f: F) -> Vec<T>
+where
+    A: Send,
+    B: Send,
+    T: Send,
+    F: Fn(usize, &mut [A], &mut [B]) -> T + Sync,
+{
+    assert_eq!(a.len(), b.len());
+    if threads <= 1 || a.len() < 2 {
+        return vec![f(0, a, b)];
+    }
+
+    let ranges = split(threads, a.len());
+    let f = &f;
+    thread::scope(|scope| {
+        let (mut rest_a, mut rest_b) = (a, b);
+        let mut handles = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let (chunk_a, tail_a) = rest_a.split_at_mut(range.len());
+            let (chunk_b, tail_b) = rest_b.split_at_mut(range.len());
+            (rest_a, rest_b) = (tail_a, tail_b);
+            handles.push(scope.spawn(move || f(range.start, chunk_a, chunk_b)));
+        }
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    })
+}
+
+fn split(threads: usize, len: usize) -> Vec<Range<usize>> {
+    let count = threads.min(len);
+    let chunk = len / count;
+    let remainder = len % count;
+    let mut start = 0;
+    (0..count)
+        .map(|k| {
+            let end = start + chunk + usize::from(k < remainder);
+            let range = start..end;
+            start = end;
+            range
+        })
+        .collect()
+}
@@ -0,0 +1,127 @@
+use crate::color::Color;
+use crate::core::Particle;
+use crate::core::ParticleId;
+use crate::vector2::Vector2;
+
+/// All particles of a simulation as a structure of arrays.
+///
+/// The hot loops only touch the arrays they need, e.g. the broadphase reads
+/// positions and radii without pulling colors and sleep state into the cache.
+/// `Particle` is still used to add and read back single particles.
+#[derive(Debug, Default, Clone)]
+pub struct ParticleStore {
+    pub ids: Vec<ParticleId>,
+    pub positions: Vec<Vector2>,
+    pub velocities: Vec<Vector2>,
+    pub radii: Vec<f64>,
+    pub masses: Vec<f64>,
+    pub colors: Vec<Color>,
+    pub asleep: Vec<bool>,
+    pub sleep_times: Vec<f64>,
+    pub clusters: Vec<Option<usize>>,
+}
+
+impl ParticleStore {
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Appends `p` with the id it has, simulations go through `Simulation::add_particle`.
+    pub(crate) fn push(&mut self, p: Particle) {
+        self.ids.push(p.id);
+        self.positions.push(p.position);
+        self.velocities.push(p.velocity);
+        self.radii.push(p.radius);
+        self.masses.push(p.mass);
+        self.colors.push(p.color);
+        self.asleep.push(p.asleep);
+        self.sleep_times.push(p.sleep_time);
+        self.clusters.push(p.cluster);
+    }
+
+    pub fn get(&self, index: usize) -> Particle {
+        Particle {
+            id: self.ids[index],
+            mass: self.masses[index],
+            position: self.positions[index],
+            velocity: self.velocities[index],
+            radius: self.radii[index],
+            color: self.colors[index],
+            asleep: self.asleep[index],
+            sleep_time: self.sleep_times[index],
+            cluster: self.clusters[index],
+        }
+    }
+
+    pub fn set(&mut self, index: usize, p: Particle) {
+        self.ids[index] = p.id;
+        self.masses[index] = p.mass;
+        self.positions[index] = p.position;
+        self.velocities[index] = p.velocity;
+        self.radii[index] = p.radius;
+        self.colors[index] = p.color;
+        self.asleep[index] = p.asleep;
+        self.sleep_times[index] = p.sleep_time;
+        self.clusters[index] = p.cluster;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Particle> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    /// Sleeping particles act as immovable obstacles.
+    #[inline]
+    pub fn inverse_mass(&self, index: usize) -> f64 {
+        if self.asleep[index] {
+            0.0
+        } else {
+            1.0 / self.masses[index]
+        }
+    }
+
+    pub fn max_radius(&self) -> f64 {
+        self.radii.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// Keeps the particles with `keep[index]` set, in their current order.
+    pub fn retain(&mut self, keep: &[bool]) {
+        retain_by(&mut self.ids, keep);
+        retain_by(&mut self.positions, keep);
+        retain_by(&mut self.velocities, keep);
+        retain_by(&mut self.radii, keep);
+        retain_by(&mut self.masses, keep);
+        retain_by(&mut self.colors, keep);
+        retain_by(&mut self.asleep, keep);
+        retain_by(&mut self.sleep_times, keep);
+        retain_by(&mut self.clusters, keep);
+    }
+
+    /// Reorders the particles so that the new index `k` holds the old `order[k]`.
+    pub fn permute(&mut self, order: &[usize]) {
+        permute_by(&mut self.ids, order);
+        permute_by(&mut self.positions, order);
+        permute_by(&mut self.velocities, order);
+        permute_by(&mut self.radii, order);
+        permute_by(&mut self.masses, order);
+        permute_by(&mut self.colors, order);
+        permute_by(&mut self.asleep, order);
+        permute_by(&mut self.sleep_times, order);
+        permute_by(&mut self.clusters, order);
+    }
+}
+
+fn permute_by<T: Copy>(items: &mut Vec<T>, order: &[usize]) {
+    *items = order.iter().map(|&i| items[i]).collect();
+}
+
+fn retain_by<T>(items: &mut Vec<T>, keep: &[bool]) {
+    let mut index = 0;
+    items.retain(|_| {
+        index += 1;
+        keep[index - 1]
+    });
+}
@@ -0,0 +1,232 @@
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::color::BLACK;
+use crate::color::BOUNDARY;
+use crate::color::Color;
+use crate::color::LETTERBOX;
+use crate::color::RED;
+use crate::color::WHITE;
+use crate::color::YELLOW;
+use crate::constraint::Constraint;
+use crate::headless::FrameSequence;
+use crate::headless::FrameWriter;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+use crate::vector2::dot;
+use crate::viewport::Viewport;
+
+/// RGBA image drawn on the CPU, with anti-aliased edges.
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<[f32; 4]>, // row major, top row first
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: vec![[0.0; 4]; width * height],
+        }
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        self.pixels.fill([color.r, color.g, color.b, color.a]);
+    }
+
+    /// Blends `color` over the pixel, `coverage` is the covered fraction of it.
+    fn blend(&mut self, x: usize, y: usize, color: Color, coverage: f64) {
+        let alpha = color.a * coverage.clamp(0.0, 1.0) as f32;
+        let pixel = &mut self.pixels[y * self.width + x];
+        let [r, g, b, a] = *pixel;
+        *pixel = [
+            r + (color.r - r) * alpha,
+            g + (color.g - g) * alpha,
+            b + (color.b - b) * alpha,
+            a + (1.0 - a) * alpha,
+        ];
+    }
+
+    /// Calls `coverage` for the pixels in the box around the shape, the pixel
+    /// centers are at half integers.
+    fn fill_with(
+        &mut self,
+        min: Vector2,
+        max: Vector2,
+        color: Color,
+        coverage: impl Fn(Vector2) -> f64,
+    ) {
+        let x0 = min.x.floor().max(0.0) as usize;
+        let y0 = min.y.floor().max(0.0) as usize;
+        let x1 = (max.x.ceil().max(0.0) as usize).min(self.width);
+        let y1 = (max.y.ceil().max(0.0) as usize).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let c = coverage(Vector2::new(x as f64 + 0.5, y as f64 + 0.5));
+                if c > 0.0 {
+                    self.blend(x, y, color, c);
+                }
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, center: Vector2, radius: f64, color: Color) {
+        // keep tiny particles visible as a dim pixel
+        let radius = radius.max(0.5);
+        let extent = Vector2::new(radius + 1.0, radius + 1.0);
+        self.fill_with(center - extent, center + extent, color, |p| {
+            radius + 0.5 - (p - center).length()
+        });
+    }
+
+    /// Fills the rectangle from `min` to `max`, edges between pixel centers
+    /// blend by the covered fraction.
+    pub fn fill_rect(&mut self, min: Vector2, max: Vector2, color: Color) {
+        self.fill_with(min, max, color, |p| {
+            let x = (p.x + 0.5).min(max.x) - (p.x - 0.5).max(min.x);
+            let y = (p.y + 0.5).min(max.y) - (p.y - 0.5).max(min.y);
+            x.clamp(0.0, 1.0) * y.clamp(0.0, 1.0)
+        });
+    }
+
+    pub fn draw_line(&mut self, a: Vector2, b: Vector2, width: f64, color: Color) {
+        let half = width / 2.0;
+        let extent = Vector2::new(half + 1.0, half + 1.0);
+        let min = Vector2::new(a.x.min(b.x), a.y.min(b.y)) - extent;
+        let max = Vector2::new(a.x.max(b.x), a.y.max(b.y)) + extent;
+        let ab = b - a;
+        let length_squared = ab.length_squared();
+        self.fill_with(min, max, color, |p| {
+            let t = if length_squared > 0.0 {
+                (dot(p - a, ab) / length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            half + 0.5 - (p - (a + ab * t)).length()
+        });
+    }
+
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|p| p.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect()
+    }
+
+    /// Writes the image with `comment` as a text chunk.
+    pub fn save_png(&self, path: &Path, comment: &str) -> io::Result<()> {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .add_text_chunk("Comment".to_string(), comment.to_string())
+            .map_err(io::Error::other)?;
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.to_rgba8())
+            .map_err(io::Error::other)
+    }
+}
+
+/// Draws the boundary, constraints, particles and trails like the window does,
+/// with letterbox bars where the canvas has another aspect ratio than the view.
+pub fn render_frame(sim: &Simulation, canvas: &mut Canvas) {
+    let viewport = Viewport::new(sim.view, canvas.width as f64, canvas.height as f64);
+    canvas.clear(BLACK);
+
+    let b = sim.boundary;
+    let corners = [
+        b.min,
+        Vector2::new(b.max.x, b.min.y),
+        b.max,
+        Vector2::new(b.min.x, b.max.y),
+    ];
+    for k in 0..4 {
+        let a = viewport.to_screen(corners[k]);
+        let b = viewport.to_screen(corners[(k + 1) % 4]);
+        canvas.draw_line(a, b, 1.0, BOUNDARY);
+    }
+
+    for c in &sim.constraints {
+        let Some((i, j)) = c.indices(&sim.ids) else {
+            continue;
+        };
+        let color = match c {
+            Constraint::Spring { .. } => YELLOW,
+            Constraint::Distance { .. } => WHITE,
+        };
+        canvas.draw_line(
+            viewport.to_screen(sim.particles.positions[i]),
+            viewport.to_screen(sim.particles.positions[j]),
+            1.0,
+            color,
+        );
+    }
+
+    let scale = viewport.scale().x;
+    let p = &sim.particles;
+    for i in 0..p.len() {
+        let center = viewport.to_screen(p.positions[i]);
+        canvas.fill_circle(center, scale * p.radii[i], p.colors[i]);
+    }
+
+    for trail in sim.trails.values() {
+        for segment in trail.windows(2) {
+            canvas.draw_line(
+                viewport.to_screen(segment[0]),
+                viewport.to_screen(segment[1]),
+                1.0,
+                RED,
+            );
+        }
+    }
+
+    // cover what is drawn outside the view, like the window's letterbox
+    let r = viewport.screen_rect();
+    let (w, h) = (canvas.width as f64, canvas.height as f64);
+    let bars = [
+        (Vector2::new(0.0, 0.0), Vector2::new(w, r.min.y)),
+        (Vector2::new(0.0, r.max.y), Vector2::new(w, h)),
+        (Vector2::new(0.0, r.min.y), Vector2::new(r.min.x, r.max.y)),
+        (Vector2::new(r.max.x, r.min.y), Vector2::new(w, r.max.y)),
+    ];
+    for (min, max) in bars {
+        canvas.fill_rect(min, max, LETTERBOX);
+    }
+}
+
+/// Draws PNG frames of a fixed size.
+pub struct PngFrames {
+    canvas: Canvas,
+}
+
+impl FrameWriter for PngFrames {
+    const EXTENSION: &'static str = "png";
+
+    fn write(&mut self, path: &Path, sim: &Simulation) -> io::Result<()> {
+        render_frame(sim, &mut self.canvas);
+        let comment = format!(
+            "simple_fluids time {} step {} seed {} stream {}",
+            sim.time,
+            sim.steps,
+            sim.rng.seed(),
+            sim.rng.stream()
+        );
+        self.canvas.save_png(path, &comment)
+    }
+}
+
+/// Writes numbered PNG frames `base_00000.png`, `base_00001.png`, ...
+pub type PngSequence = FrameSequence<PngFrames>;
+
+impl PngSequence {
+    pub fn create(base: &Path, width: usize, height: usize) -> io::Result<PngSequence> {
+        let canvas = Canvas::new(width, height);
+        FrameSequence::new(base, PngFrames { canvas })
+    }
+}
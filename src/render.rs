@@ -1,47 +1,200 @@
+use std::path::PathBuf;
+
 use macroquad::prelude::*;
 
-use crate::core::Particle;
+use crate::camera::Camera;
+use crate::color::BOUNDARY;
+use crate::color::LETTERBOX;
+use crate::constraint::Constraint;
 use crate::core::Rectangle;
+use crate::headless::Observer;
+use crate::headless::observe_due;
+use crate::interaction::MouseTools;
+use crate::particle_store::ParticleStore;
 use crate::simulation::Simulation;
+use crate::snapshot::Snapshot;
+use crate::svg::save_svg;
 use crate::vector2::Vector2;
+use crate::viewport::Viewport;
+
+const MAX_SPEED: f64 = 64.0;
+
+/// Time controls of the window.
+struct Playback {
+    paused: bool,
+    speed: f64,          // target ratio of simulated to real time
+    measured_speed: f64, // simulated over real time since the last reset
+    show_hud: bool,
+}
 
-pub async fn run(sim: &mut Simulation, fixed_dt: f64) {
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            paused: false,
+            speed: 1.0,
+            measured_speed: 0.0,
+            show_hud: true,
+        }
+    }
+}
+
+/// Runs the simulation in the window. Like the headless runner it calls the
+/// observers at the start and then every `output_interval` steps, unless they
+/// have their own interval.
+///
+/// Space pauses, N advances one step while paused, + and - double and halve
+/// the speed, R resets to the state at the start and ends the output, and H
+/// toggles the HUD. The mouse tools are described at `MouseTools`, zooming and
+/// panning at `Camera`.
+pub async fn run(
+    sim: &mut Simulation,
+    fixed_dt: f64,
+    mut observers: Vec<Box<dyn Observer>>,
+    output_interval: u64,
+) {
+    let initial = Snapshot::capture(sim);
+    let mut playback = Playback::default();
+    let mut tools = MouseTools::new(sim);
+    let mut camera = Camera::new(sim.view);
     let mut real_time_elapsed = 0.0;
     let mut simulated_time = 0.0;
     let mut pending_sim_time = 0.0;
-    let mut sim_speed = 0.0;
+    let mut steps: u64 = 0;
+    notify(&mut observers, sim, steps, output_interval);
     loop {
         clear_background(BLACK);
         let dt = get_frame_time() as f64;
-        real_time_elapsed += dt;
-        pending_sim_time += dt;
-
-        while pending_sim_time >= fixed_dt {
-            sim.update(fixed_dt);
-            pending_sim_time -= fixed_dt;
-            simulated_time += fixed_dt;
-            // if below framerate limit don't simulate multiple steps
-            if dt > 1.0 / 60.0 {
-                break;
+
+        if is_key_pressed(KeyCode::Space) {
+            playback.paused = !playback.paused;
+        }
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+            playback.speed = (playback.speed * 2.0).min(MAX_SPEED);
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+            playback.speed = (playback.speed / 2.0).max(1.0 / MAX_SPEED);
+        }
+        if is_key_pressed(KeyCode::H) {
+            playback.show_hud = !playback.show_hud;
+        }
+        if is_key_pressed(KeyCode::R) {
+            finish(&mut observers, sim);
+            if let Err(err) = initial.apply(sim) {
+                eprintln!("Error: reset failed, {}", err);
+            }
+            real_time_elapsed = 0.0;
+            simulated_time = 0.0;
+            pending_sim_time = 0.0;
+            steps = 0;
+        }
+
+        camera.handle_input(sim);
+        tools.handle_input(sim, camera.viewport());
+
+        if playback.paused {
+            if is_key_pressed(KeyCode::N) {
+                tools.apply(sim, fixed_dt);
+                sim.update(fixed_dt);
+                steps += 1;
+                notify(&mut observers, sim, steps, output_interval);
+            }
+        } else {
+            real_time_elapsed += dt;
+            pending_sim_time += dt * playback.speed;
+
+            let mut frame_steps = 0.0;
+            while pending_sim_time >= fixed_dt {
+                tools.apply(sim, fixed_dt);
+                sim.update(fixed_dt);
+                pending_sim_time -= fixed_dt;
+                simulated_time += fixed_dt;
+                steps += 1;
+                frame_steps += 1.0;
+                notify(&mut observers, sim, steps, output_interval);
+                // if below framerate limit don't simulate more steps than the speed asks for
+                if dt > 1.0 / 60.0 && frame_steps >= playback.speed.ceil() {
+                    break;
+                }
             }
         }
+        if real_time_elapsed > 0.0 {
+            playback.measured_speed = simulated_time / real_time_elapsed;
+        }
 
-        sim_speed = simulated_time / real_time_elapsed;
+        camera.follow(sim);
+        let viewport = camera.viewport();
+        if is_key_pressed(KeyCode::F12) {
+            save_screenshot(sim, viewport);
+        }
 
-        render_particles(&sim.particles, &sim.view);
-        render_trails(sim);
-        render_info(Some(sim_speed));
+        render_boundary(sim.boundary, &viewport);
+        render_constraints(sim, &viewport);
+        render_particles(&sim.particles, &viewport);
+        render_trails(sim, &viewport);
+        render_letterbox(&viewport);
+        tools.draw(sim, viewport);
+        if playback.show_hud {
+            render_info(sim, Some((&playback, &tools, &camera)));
+        }
 
         next_frame().await;
     }
 }
 
-fn render_info(sim_speed: Option<f64>) {
-    let fps_text = format!("FPS: {:.1}", get_fps());
-    draw_text(&fps_text, 10.0, 20.0, 20.0, WHITE);
-    if let Some(speed) = sim_speed {
-        let sim_speed_text: String = format!("Speed: {:.4}", speed);
-        draw_text(&sim_speed_text, 10.0, 40.0, 20.0, WHITE);
+/// Stops all output after the first error, the window keeps running.
+fn notify(observers: &mut Vec<Box<dyn Observer>>, sim: &Simulation, step: u64, interval: u64) {
+    if let Err(err) = observe_due(observers, sim, step, interval) {
+        eprintln!("Error: {}, output stopped", err);
+        observers.clear();
+    }
+}
+
+/// Ends the output at a reset, so every file holds a single timeline.
+fn finish(observers: &mut Vec<Box<dyn Observer>>, sim: &Simulation) {
+    if observers.is_empty() {
+        return;
+    }
+    for observer in observers.iter_mut() {
+        if let Err(err) = observer.finish(sim) {
+            eprintln!("Error: {}", err);
+        }
+    }
+    observers.clear();
+    eprintln!("Output stopped at the reset");
+}
+
+/// Writes what the window shows to `frame_<step>.svg` in the working directory.
+fn save_screenshot(sim: &Simulation, viewport: Viewport) {
+    let path = PathBuf::from(format!("frame_{:08}.svg", sim.steps));
+    match save_svg(&path, sim, viewport) {
+        Ok(()) => println!("Saved {}", path.display()),
+        Err(err) => eprintln!("Error: {}: {}", path.display(), err),
+    }
+}
+
+fn render_info(sim: &Simulation, controls: Option<(&Playback, &MouseTools, &Camera)>) {
+    let mut lines = vec![format!("FPS: {:.1}", get_fps())];
+    if let Some((playback, tools, camera)) = controls {
+        lines.push(format!(
+            "Speed: {:.4} (target {}x)",
+            playback.measured_speed, playback.speed
+        ));
+        if playback.paused {
+            lines.push("Paused, N steps".to_string());
+        }
+        lines.push(tools.status());
+        lines.push(camera.status());
+    }
+    lines.push(format!("Time: {:.4}  Step: {}", sim.time, sim.steps));
+    if sim.sleep.is_some() {
+        lines.push(format!(
+            "Sleeping: {}/{}",
+            sim.sleeping_count(),
+            sim.particles.len()
+        ));
+    }
+    for (k, line) in lines.iter().enumerate() {
+        draw_text(line, 10.0, 20.0 * (k + 1) as f32, 20.0, WHITE);
     }
 }
 
@@ -51,60 +204,92 @@ pub async fn run_realtime(sim: &mut Simulation) {
         let dt = get_frame_time() as f64;
         sim.update(dt);
 
-        render_particles(&sim.particles, &sim.view);
-        render_trails(sim);
-        render_info(None);
+        let viewport = screen_viewport(&sim.view);
+        render_boundary(sim.boundary, &viewport);
+        render_constraints(sim, &viewport);
+        render_particles(&sim.particles, &viewport);
+        render_trails(sim, &viewport);
+        render_letterbox(&viewport);
+        render_info(sim, None);
 
         next_frame().await;
     }
 }
 
-pub fn render_particles(particle: &Vec<Particle>, view: &Rectangle) {
-    for p in particle {
+pub fn render_particles(particles: &ParticleStore, viewport: &Viewport) {
+    let scale = viewport.scale().x;
+    for ((&position, &radius), &color) in particles
+        .positions
+        .iter()
+        .zip(&particles.radii)
+        .zip(&particles.colors)
+    {
+        let center = viewport.to_screen(position);
         draw_circle(
-            to_screen(p.position, view).x as f32,
-            to_screen(p.position, view).y as f32,
-            (get_scale(view).x * p.radius) as f32,
-            p.color,
+            center.x as f32,
+            center.y as f32,
+            (scale * radius) as f32,
+            color.into(),
         );
     }
 }
 
-fn get_screen_size() -> Vector2 {
-    Vector2 {
-        x: screen_width() as f64,
-        y: screen_height() as f64,
-    }
+pub fn render_boundary(boundary: Rectangle, viewport: &Viewport) {
+    let a = viewport.to_screen(Vector2::new(boundary.min.x, boundary.max.y));
+    let b = viewport.to_screen(Vector2::new(boundary.max.x, boundary.min.y));
+    draw_rectangle_lines(
+        a.x as f32,
+        a.y as f32,
+        (b.x - a.x) as f32,
+        (b.y - a.y) as f32,
+        1.0,
+        BOUNDARY.into(),
+    );
 }
 
-fn get_scale(view: &Rectangle) -> Vector2 {
-    let screen = get_screen_size();
-    (screen * Vector2::new(1.0, -1.0)) / (view.max - view.min)
+/// Covers what lies outside the view in the pixel area of the viewport.
+pub fn render_letterbox(viewport: &Viewport) {
+    let r = viewport.screen_rect();
+    let (w, h) = (viewport.width as f32, viewport.height as f32);
+    let (x0, y0) = (r.min.x as f32, r.min.y as f32);
+    let (x1, y1) = (r.max.x as f32, r.max.y as f32);
+    draw_rectangle(0.0, 0.0, w, y0, LETTERBOX.into());
+    draw_rectangle(0.0, y1, w, h - y1, LETTERBOX.into());
+    draw_rectangle(0.0, y0, x0, y1 - y0, LETTERBOX.into());
+    draw_rectangle(x1, y0, w - x1, y1 - y0, LETTERBOX.into());
 }
 
-fn to_screen(p: Vector2, view: &Rectangle) -> Vector2 {
-    let screen = get_screen_size();
-    let s = get_scale(view);
-    let t = Vector2::new(screen.x, 0.0) - s * view.max;
-    s * p + t
+fn screen_viewport(view: &Rectangle) -> Viewport {
+    Viewport::new(*view, screen_width() as f64, screen_height() as f64)
 }
 
-// S * LB + T = (0, screen.y)
-// S * RT + T = (screen.x, 0)
-//
-// S = screen*(1,-1)/(LB-RT);
-
-fn render_trail(view: &Rectangle, trail: &Vec<Vector2>) {
+pub fn render_trail(viewport: &Viewport, trail: &[Vector2]) {
     for i in 0..trail.len().saturating_sub(1) {
-        let a = to_screen(trail[i], view);
-        let b = to_screen(trail[i + 1], view);
+        let a = viewport.to_screen(trail[i]);
+        let b = viewport.to_screen(trail[i + 1]);
 
         draw_line(a.x as f32, a.y as f32, b.x as f32, b.y as f32, 1.0, RED);
     }
 }
 
-fn render_trails(sim: &mut Simulation) {
+fn render_trails(sim: &Simulation, viewport: &Viewport) {
     for trail in sim.trails.values() {
-        render_trail(&sim.view, trail);
+        render_trail(viewport, trail);
+    }
+}
+
+fn render_constraints(sim: &Simulation, viewport: &Viewport) {
+    for c in &sim.constraints {
+        let Some((i, j)) = c.indices(&sim.ids) else {
+            continue;
+        };
+        let a = viewport.to_screen(sim.particles.positions[i]);
+        let b = viewport.to_screen(sim.particles.positions[j]);
+        let color = match c {
+            Constraint::Spring { .. } => YELLOW,
+            Constraint::Distance { .. } => WHITE,
+        };
+
+        draw_line(a.x as f32, a.y as f32, b.x as f32, b.y as f32, 1.0, color);
     }
 }
@@ -0,0 +1,152 @@
+use macroquad::prelude::*;
+
+use crate::render::render_boundary;
+use crate::render::render_letterbox;
+use crate::render::render_particles;
+use crate::render::render_trail;
+use crate::vector2::Vector2;
+use crate::viewport::Viewport;
+use crate::viewport::default_size;
+use crate::xyz::Trajectory;
+
+const TIMELINE_HEIGHT: f32 = 24.0;
+const TRAIL_LENGTH: usize = 500; // frames
+const MAX_SPEED: f64 = 64.0;
+
+/// Plays a recorded trajectory back in the window.
+///
+/// Space plays and pauses, left and right step a frame, up and down double
+/// and halve the speed, Home and End jump to the first and last frame.
+/// Clicking or dragging on the timeline at the bottom jumps to that time.
+pub async fn run_replay(trajectory: &Trajectory) {
+    let frames = &trajectory.frames;
+    let last_frame = frames.len() - 1;
+    let (start, end) = (frames[0].time, frames[last_frame].time);
+
+    let (width, height) = default_size(frames[0].boundary);
+    request_new_screen_size(width as f32, height as f32 + TIMELINE_HEIGHT);
+
+    let mut time = start;
+    let mut frame = 0;
+    let mut playing = true;
+    let mut speed: f64 = 1.0;
+    let mut scrubbing = false;
+    let mut trails: Vec<Vec<Vector2>> = Vec::new();
+    let mut trails_frame = None;
+
+    loop {
+        clear_background(BLACK);
+
+        if is_key_pressed(KeyCode::Space) {
+            if !playing && frame == last_frame {
+                time = start;
+            }
+            playing = !playing;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            speed = (speed * 2.0).min(MAX_SPEED);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            speed = (speed / 2.0).max(1.0 / MAX_SPEED);
+        }
+        let jump = if is_key_pressed(KeyCode::Right) {
+            Some((frame + 1).min(last_frame))
+        } else if is_key_pressed(KeyCode::Left) {
+            Some(frame.saturating_sub(1))
+        } else if is_key_pressed(KeyCode::Home) {
+            Some(0)
+        } else if is_key_pressed(KeyCode::End) {
+            Some(last_frame)
+        } else {
+            None
+        };
+
+        let timeline = timeline_rect();
+        let mouse = Vec2::from(mouse_position());
+        // the whole strip below the view takes clicks, not just the thin bar
+        if is_mouse_button_pressed(MouseButton::Left)
+            && mouse.y >= screen_height() - TIMELINE_HEIGHT
+        {
+            scrubbing = true;
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            scrubbing = false;
+        }
+
+        if let Some(target) = jump {
+            playing = false;
+            frame = target;
+            time = frames[frame].time;
+        } else if scrubbing {
+            let fraction = ((mouse.x - timeline.x) / timeline.w).clamp(0.0, 1.0) as f64;
+            time = start + fraction * (end - start);
+            frame = trajectory.frame_at(time);
+        } else if playing {
+            time += get_frame_time() as f64 * speed;
+            if time >= end {
+                time = end;
+                playing = false;
+            }
+            frame = trajectory.frame_at(time);
+        }
+
+        if trails_frame != Some(frame) {
+            trails = trajectory
+                .tracked
+                .iter()
+                .map(|&id| trajectory.trail(id, frame, TRAIL_LENGTH))
+                .collect();
+            trails_frame = Some(frame);
+        }
+
+        let current = &frames[frame];
+        let viewport = Viewport::new(
+            current.boundary,
+            screen_width() as f64,
+            (screen_height() - TIMELINE_HEIGHT) as f64,
+        );
+        render_boundary(current.boundary, &viewport);
+        render_particles(&current.particles, &viewport);
+        for trail in &trails {
+            render_trail(&viewport, trail);
+        }
+        render_letterbox(&viewport);
+        render_timeline(timeline, (time - start) / (end - start));
+
+        let status = if playing { "Playing" } else { "Paused" };
+        let lines = [
+            format!("Frame: {}/{}", frame + 1, frames.len()),
+            format!("Time: {:.4}  Step: {}", current.time, current.step),
+            format!("{} at {}x", status, speed),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            draw_text(line, 10.0, 20.0 * (k + 1) as f32, 20.0, WHITE);
+        }
+
+        next_frame().await;
+    }
+}
+
+fn timeline_rect() -> Rect {
+    Rect::new(
+        10.0,
+        screen_height() - TIMELINE_HEIGHT + 8.0,
+        screen_width() - 20.0,
+        TIMELINE_HEIGHT - 16.0,
+    )
+}
+
+/// `progress` is the played fraction, NaN for a single frame.
+fn render_timeline(timeline: Rect, progress: f64) {
+    let progress = if progress.is_finite() { progress } else { 1.0 };
+    draw_rectangle(timeline.x, timeline.y, timeline.w, timeline.h, DARKGRAY);
+    let played = timeline.w * progress.clamp(0.0, 1.0) as f32;
+    draw_rectangle(timeline.x, timeline.y, played, timeline.h, GRAY);
+    draw_rectangle(
+        timeline.x + played - 2.0,
+        timeline.y - 4.0,
+        4.0,
+        timeline.h + 8.0,
+        WHITE,
+    );
+}
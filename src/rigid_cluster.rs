@@ -0,0 +1,174 @@
+use crate::particle_store::ParticleStore;
+use crate::sleep;
+use crate::vector2::Vector2;
+use crate::vector2::cross;
+use crate::vector2::dot;
+
+/// A rigid body made of several disks.
+///
+/// The members are integrated like free particles. Before the contacts are
+/// solved the cluster collects their momentum, and every contact impulse on a
+/// member goes into the shared linear and angular velocity with the effective
+/// mass of the body at the contact. Afterwards the cluster puts the members
+/// back into their rigid arrangement.
+#[derive(Debug, Clone)]
+pub struct RigidCluster {
+    pub members: Vec<usize>,
+    pub body_offsets: Vec<Vector2>, // member offsets from the center of mass at angle 0
+    pub center: Vector2,
+    pub angle: f64,
+    pub velocity: Vector2,
+    pub angular_velocity: f64,
+    mass: f64,
+    inertia: f64,
+}
+
+impl RigidCluster {
+    /// Freezes the current arrangement of `members` into a rigid body.
+    pub fn new(members: Vec<usize>, particles: &ParticleStore) -> RigidCluster {
+        let mass: f64 = members.iter().map(|&i| particles.masses[i]).sum();
+        let center = weighted_center(&members, particles) / mass;
+        let body_offsets: Vec<Vector2> = members
+            .iter()
+            .map(|&i| particles.positions[i] - center)
+            .collect();
+
+        let mut cluster = RigidCluster::from_body(members, body_offsets, particles);
+        cluster.center = center;
+        cluster.collect_momentum(particles);
+        cluster
+    }
+
+    /// Cluster with the given arrangement at rest at the origin, e.g. to be
+    /// restored from a snapshot.
+    pub fn from_body(
+        members: Vec<usize>,
+        body_offsets: Vec<Vector2>,
+        particles: &ParticleStore,
+    ) -> RigidCluster {
+        let mass: f64 = members.iter().map(|&i| particles.masses[i]).sum();
+
+        // point masses on the offsets plus the inertia of each disk
+        let inertia = members
+            .iter()
+            .zip(&body_offsets)
+            .map(|(&i, r)| {
+                let radius = particles.radii[i];
+                particles.masses[i] * (r.length_squared() + 0.5 * radius * radius)
+            })
+            .sum();
+
+        RigidCluster {
+            members,
+            body_offsets,
+            center: Vector2::ZERO,
+            angle: 0.0,
+            velocity: Vector2::ZERO,
+            angular_velocity: 0.0,
+            mass,
+            inertia,
+        }
+    }
+
+    pub fn mass(&self) -> f64 {
+        self.mass
+    }
+
+    pub fn inertia(&self) -> f64 {
+        self.inertia
+    }
+
+    /// Takes the center and the velocity from the freely integrated members,
+    /// before the contacts are solved.
+    pub fn collect(&mut self, particles: &ParticleStore) {
+        self.center = weighted_center(&self.members, particles) / self.mass;
+        self.collect_momentum(particles);
+    }
+
+    /// Inverse of the effective mass for an impulse along `normal` at `point`,
+    /// `1/M + (r x n)^2 / I`.
+    pub fn inverse_mass_at(&self, point: Vector2, normal: Vector2) -> f64 {
+        let rn = cross(point - self.center, normal);
+        1.0 / self.mass + rn * rn / self.inertia
+    }
+
+    /// Applies `impulse` at `point` to the whole body and updates the members'
+    /// velocities, their positions stay until `update`.
+    pub fn apply_impulse(
+        &mut self,
+        particles: &mut ParticleStore,
+        point: Vector2,
+        impulse: Vector2,
+    ) {
+        self.velocity += impulse / self.mass;
+        self.angular_velocity += cross(point - self.center, impulse) / self.inertia;
+        for &i in &self.members {
+            let r = particles.positions[i] - self.center;
+            particles.velocities[i] = self.velocity + self.angular_velocity * r.perp();
+        }
+    }
+
+    /// Updates the cluster from its freely moved members and snaps them back
+    /// into the rigid arrangement.
+    pub fn update(&mut self, particles: &mut ParticleStore) {
+        if self.members.iter().all(|&i| particles.asleep[i]) {
+            return;
+        }
+        for &i in &self.members {
+            if particles.asleep[i] {
+                sleep::wake(particles, i);
+            }
+        }
+
+        self.center = weighted_center(&self.members, particles) / self.mass;
+
+        // best fitting rotation of the body offsets onto the current offsets
+        let (mut sin_sum, mut cos_sum) = (0.0, 0.0);
+        for (&i, b) in self.members.iter().zip(&self.body_offsets) {
+            let r = particles.positions[i] - self.center;
+            sin_sum += particles.masses[i] * cross(*b, r);
+            cos_sum += particles.masses[i] * dot(*b, r);
+        }
+        if sin_sum != 0.0 || cos_sum != 0.0 {
+            self.angle = sin_sum.atan2(cos_sum);
+        }
+
+        self.collect_momentum(particles);
+        self.apply_to_members(particles);
+    }
+
+    fn collect_momentum(&mut self, particles: &ParticleStore) {
+        let momentum = self.members.iter().fold(Vector2::ZERO, |acc, &i| {
+            acc + particles.masses[i] * particles.velocities[i]
+        });
+        self.velocity = momentum / self.mass;
+
+        // the members' velocities only carry the point masses, not the spin of each disk
+        let (mut angular_momentum, mut point_inertia) = (0.0, 0.0);
+        for &i in &self.members {
+            let r = particles.positions[i] - self.center;
+            angular_momentum +=
+                particles.masses[i] * cross(r, particles.velocities[i] - self.velocity);
+            point_inertia += particles.masses[i] * r.length_squared();
+        }
+        self.angular_velocity = if point_inertia > 0.0 {
+            angular_momentum / point_inertia
+        } else {
+            0.0
+        };
+    }
+
+    fn apply_to_members(&self, particles: &mut ParticleStore) {
+        for (&i, b) in self.members.iter().zip(&self.body_offsets) {
+            let r = b.rotated(self.angle);
+            particles.positions[i] = self.center + r;
+            particles.velocities[i] = self.velocity + self.angular_velocity * r.perp();
+        }
+    }
+}
+
+fn weighted_center(members: &[usize], particles: &ParticleStore) -> Vector2 {
+    members.iter().fold(Vector2::ZERO, |acc, &i| {
+        acc + particles.masses[i] * particles.positions[i]
+    })
+}
@@ -0,0 +1,79 @@
+use rand::RngCore;
+use rand::SeedableRng;
+use rand::rand_core::impls;
+use rand_pcg::Pcg64;
+
+pub const DEFAULT_SEED: u64 = 12345;
+
+/// Seedable random number generator owned by a simulation.
+///
+/// Generators with the same seed but different streams produce independent
+/// sequences, e.g. for replicas run side by side. The generator counts its
+/// draws so that its exact state can be saved as `(seed, stream, draws)` and
+/// restored later.
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    seed: u64,
+    stream: u64,
+    draws: u128,
+    rng: Pcg64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> SimRng {
+        SimRng::with_stream(seed, 0)
+    }
+
+    pub fn with_stream(seed: u64, stream: u64) -> SimRng {
+        // spread the seed over the whole 128 bit state
+        let mut seeder = Pcg64::seed_from_u64(seed);
+        let state = ((seeder.next_u64() as u128) << 64) | seeder.next_u64() as u128;
+        SimRng {
+            seed,
+            stream,
+            draws: 0,
+            rng: Pcg64::new(state, stream as u128),
+        }
+    }
+
+    /// Recreates a generator after `draws` 64 bit draws.
+    pub fn restore(seed: u64, stream: u64, draws: u128) -> SimRng {
+        let mut rng = SimRng::with_stream(seed, stream);
+        rng.rng.advance(draws);
+        rng.draws = draws;
+        rng
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&self) -> u64 {
+        self.stream
+    }
+
+    pub fn draws(&self) -> u128 {
+        self.draws
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        SimRng::new(DEFAULT_SEED)
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+}
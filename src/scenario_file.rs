@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rand_distr::Distribution;
+use rand_distr::Normal;
+
+use crate::color::Color;
+use crate::color::GREEN;
+use crate::core::Particle;
+use crate::core::Rectangle;
+use crate::rng::SimRng;
+use crate::simulation::Simulation;
+use crate::simulation_factory::ScenarioParams;
+use crate::sleep::SleepSettings;
+use crate::uniform_grid::UniformGrid;
+use crate::vector2::Vector2;
+
+const PLACEMENT_ATTEMPTS: usize = 100;
+
+/// Scenario read from a text file of `key = value` lines.
+///
+/// Top level keys describe the box, each `[group]` section adds particles
+/// placed without overlap in a region. `#` starts a comment.
+///
+/// ```text
+/// boundary = 0 0 2 1        # min x, min y, max x, max y
+/// gravity = 0 -1
+/// restitution = 0.5
+///
+/// [group]
+/// region = 0 0 1 1
+/// count = 300
+/// radius = 0.01
+/// color = 0.93 0.07 0.27
+/// velocity = thermal 0.5    # or: vx vy, disk speed, gaussian sigma
+/// track = 0                 # indices within the group
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScenarioFile {
+    pub boundary: Rectangle,
+    pub view: Option<Rectangle>, // defaults to the boundary
+    pub gravity: Vector2,
+    pub restitution: f64,
+    pub sleep: bool,
+    pub resting_contacts: bool,
+    pub groups: Vec<ParticleGroup>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticleGroup {
+    pub line: usize, // line of the `[group]` header, for error messages
+    pub region: Rectangle,
+    pub count: usize,
+    pub radius: f64,
+    pub mass: Option<f64>, // defaults to the disk area
+    pub color: Color,
+    pub velocity: VelocityDistribution,
+    pub track: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum VelocityDistribution {
+    Constant(Vector2),
+    Disk(f64),     // uniform in a disk of the given speed
+    Gaussian(f64), // standard deviation of each component
+    Thermal(f64),  // temperature, the deviation depends on the mass
+}
+
+impl VelocityDistribution {
+    fn sample(self, rng: &mut SimRng, mass: f64) -> Vector2 {
+        match self {
+            VelocityDistribution::Constant(v) => v,
+            VelocityDistribution::Disk(speed) => Vector2::random_in_disk(rng) * speed,
+            VelocityDistribution::Gaussian(sigma) => gaussian(rng, sigma),
+            VelocityDistribution::Thermal(temperature) => {
+                gaussian(rng, (temperature / mass).sqrt())
+            }
+        }
+    }
+}
+
+fn gaussian(rng: &mut SimRng, sigma: f64) -> Vector2 {
+    if sigma == 0.0 {
+        return Vector2::ZERO;
+    }
+    let normal = Normal::new(0.0, sigma).unwrap();
+    Vector2::new(normal.sample(rng), normal.sample(rng))
+}
+
+/// Reads and builds a scenario file, errors name the file and line.
+pub fn load(path: &Path, params: &ScenarioParams) -> Result<Simulation, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    ScenarioFile::parse(&text)
+        .and_then(|file| file.build(params))
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+impl ScenarioFile {
+    pub fn parse(text: &str) -> Result<ScenarioFile, String> {
+        let mut top = Section::new(0);
+        let mut groups: Vec<Section> = Vec::new();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let content = raw.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = content.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                if name.trim() != "group" {
+                    return Err(format!("line {}: unknown section [{}]", line, name.trim()));
+                }
+                groups.push(Section::new(line));
+                continue;
+            }
+
+            let (key, value) = content
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", line))?;
+            let section = groups.last_mut().unwrap_or(&mut top);
+            section.insert(key.trim(), value.trim(), line)?;
+        }
+
+        let boundary = top.rectangle("boundary")?.ok_or("missing boundary")?;
+        let view = top.rectangle("view")?;
+        let gravity = top.vector("gravity")?.unwrap_or(Vector2::ZERO);
+        let restitution = top.number("restitution")?.unwrap_or(1.0);
+        let sleep = top.flag("sleep")?.unwrap_or(false);
+        let resting_contacts = top.flag("resting_contacts")?.unwrap_or(false);
+        top.check_unused(&[
+            "boundary",
+            "view",
+            "gravity",
+            "restitution",
+            "sleep",
+            "resting_contacts",
+        ])?;
+
+        if !(0.0..=1.0).contains(&restitution) {
+            return Err(top.error("restitution", "must be between 0 and 1"));
+        }
+
+        let groups = groups
+            .iter()
+            .map(|section| section.group(boundary))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ScenarioFile {
+            boundary,
+            view,
+            gravity,
+            restitution,
+            sleep,
+            resting_contacts,
+            groups,
+        })
+    }
+
+    /// Places the particles of all groups, later groups avoid the earlier ones.
+    pub fn build(&self, params: &ScenarioParams) -> Result<Simulation, String> {
+        let mut rng = SimRng::new(params.seed);
+        let max_radius = self.groups.iter().map(|g| g.radius).fold(0.0, f64::max);
+        let mut grid = UniformGrid::new(self.boundary, max_radius);
+        let mut particles: Vec<Particle> = Vec::new();
+        let mut tracked = Vec::new();
+
+        for group in &self.groups {
+            let first = particles.len();
+            let mass = group
+                .mass
+                .unwrap_or(std::f64::consts::PI * group.radius * group.radius);
+
+            for _ in 0..group.count {
+                let position = find_free_position(
+                    &mut rng,
+                    &grid,
+                    &particles,
+                    group.region,
+                    group.radius,
+                )
+                .ok_or_else(|| {
+                    format!(
+                        "line {}: only placed {} of {} particles, the region is too crowded",
+                        group.line,
+                        particles.len() - first,
+                        group.count
+                    )
+                })?;
+                grid.add_particle(particles.len(), position);
+                particles.push(Particle {
+                    mass,
+                    position,
+                    velocity: group.velocity.sample(&mut rng, mass),
+                    radius: group.radius,
+                    color: group.color,
+                    ..Default::default()
+                });
+            }
+            tracked.extend(group.track.iter().map(|&i| first + i));
+        }
+
+        let mut sim = Simulation {
+            view: self.view.unwrap_or(self.boundary),
+            boundary: self.boundary,
+            gravity: Vector2::new(self.gravity.x, params.gravity.unwrap_or(self.gravity.y)),
+            restitution: params.restitution.unwrap_or(self.restitution),
+            sleep: self.sleep.then(SleepSettings::default),
+            resting_contacts: self.resting_contacts,
+            rng,
+            ..Default::default()
+        };
+        let ids = sim.add_particles(particles);
+        for index in tracked {
+            sim.track(ids[index]);
+        }
+        Ok(sim)
+    }
+}
+
+fn find_free_position(
+    rng: &mut SimRng,
+    grid: &UniformGrid,
+    particles: &[Particle],
+    region: Rectangle,
+    radius: f64,
+) -> Option<Vector2> {
+    (0..PLACEMENT_ATTEMPTS)
+        .map(|_| Vector2::random_min_max(rng, region.min + radius, region.max - radius))
+        .find(|&position| {
+            grid.get_close_colliders(position).into_iter().all(|i| {
+                (position - particles[i].position).length_squared()
+                    >= (radius + particles[i].radius).powi(2)
+            })
+        })
+}
+
+/// Raw `key = value` pairs of one section with their line numbers.
+struct Section {
+    line: usize,
+    values: HashMap<String, (String, usize)>,
+}
+
+impl Section {
+    fn new(line: usize) -> Section {
+        Section {
+            line,
+            values: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, key: &str, value: &str, line: usize) -> Result<(), String> {
+        if key.is_empty() {
+            return Err(format!("line {}: missing key before `=`", line));
+        }
+        if let Some((_, first)) = self.values.get(key) {
+            return Err(format!(
+                "line {}: {} was already set on line {}",
+                line, key, first
+            ));
+        }
+        self.values
+            .insert(key.to_string(), (value.to_string(), line));
+        Ok(())
+    }
+
+    fn error(&self, key: &str, message: &str) -> String {
+        let line = self.values.get(key).map_or(self.line, |&(_, line)| line);
+        format!("line {}: {} {}", line, key, message)
+    }
+
+    fn check_unused(&self, known: &[&str]) -> Result<(), String> {
+        let mut unknown: Vec<_> = self
+            .values
+            .iter()
+            .filter(|(key, _)| !known.contains(&key.as_str()))
+            .map(|(key, &(_, line))| (line, key))
+            .collect();
+        unknown.sort();
+        match unknown.first() {
+            Some((line, key)) => Err(format!("line {}: unknown key {}", line, key)),
+            None => Ok(()),
+        }
+    }
+
+    fn words(&self, key: &str) -> Option<Vec<&str>> {
+        self.values
+            .get(key)
+            .map(|(value, _)| value.split_whitespace().collect())
+    }
+
+    fn parse_words(&self, key: &str, words: &[&str]) -> Result<Vec<f64>, String> {
+        words
+            .iter()
+            .map(|w| {
+                w.parse::<f64>()
+                    .ok()
+                    .filter(|x| x.is_finite())
+                    .ok_or_else(|| self.error(key, &format!("has an invalid number `{}`", w)))
+            })
+            .collect()
+    }
+
+    fn numbers<const N: usize>(&self, key: &str) -> Result<Option<[f64; N]>, String> {
+        let Some(words) = self.words(key) else {
+            return Ok(None);
+        };
+        let numbers = self.parse_words(key, &words)?;
+        numbers
+            .try_into()
+            .map(Some)
+            .map_err(|_| self.error(key, &format!("needs {} numbers", N)))
+    }
+
+    fn number(&self, key: &str) -> Result<Option<f64>, String> {
+        Ok(self.numbers::<1>(key)?.map(|[x]| x))
+    }
+
+    fn vector(&self, key: &str) -> Result<Option<Vector2>, String> {
+        Ok(self.numbers::<2>(key)?.map(|[x, y]| Vector2::new(x, y)))
+    }
+
+    fn rectangle(&self, key: &str) -> Result<Option<Rectangle>, String> {
+        let Some([x0, y0, x1, y1]) = self.numbers::<4>(key)? else {
+            return Ok(None);
+        };
+        if x1 <= x0 || y1 <= y0 {
+            return Err(self.error(key, "needs min x < max x and min y < max y"));
+        }
+        Ok(Some(Rectangle {
+            min: Vector2::new(x0, y0),
+            max: Vector2::new(x1, y1),
+        }))
+    }
+
+    fn flag(&self, key: &str) -> Result<Option<bool>, String> {
+        match self.values.get(key).map(|(value, _)| value.as_str()) {
+            None => Ok(None),
+            Some("true") => Ok(Some(true)),
+            Some("false") => Ok(Some(false)),
+            Some(_) => Err(self.error(key, "must be true or false")),
+        }
+    }
+
+    fn count(&self, key: &str) -> Result<Option<usize>, String> {
+        self.values
+            .get(key)
+            .map(|(value, _)| {
+                value
+                    .parse()
+                    .map_err(|_| self.error(key, "must be a whole number"))
+            })
+            .transpose()
+    }
+
+    fn color(&self, key: &str) -> Result<Option<Color>, String> {
+        let Some(words) = self.words(key) else {
+            return Ok(None);
+        };
+        let c = self.parse_words(key, &words)?;
+        if c.iter().any(|x| !(0.0..=1.0).contains(x)) {
+            return Err(self.error(key, "components must be between 0 and 1"));
+        }
+        match c[..] {
+            [r, g, b] => Ok(Some(Color::new(r as f32, g as f32, b as f32, 1.0))),
+            [r, g, b, a] => Ok(Some(Color::new(r as f32, g as f32, b as f32, a as f32))),
+            _ => Err(self.error(key, "needs r g b or r g b a")),
+        }
+    }
+
+    fn velocity(&self, key: &str) -> Result<Option<VelocityDistribution>, String> {
+        let Some(words) = self.words(key) else {
+            return Ok(None);
+        };
+        let (kind, args) = match words.first() {
+            Some(&w) if w.parse::<f64>().is_err() => (w, &words[1..]),
+            _ => ("constant", &words[..]),
+        };
+        let args = self.parse_words(key, args)?;
+        let distribution = match (kind, &args[..]) {
+            ("zero", []) => VelocityDistribution::Constant(Vector2::ZERO),
+            ("constant", &[vx, vy]) => VelocityDistribution::Constant(Vector2::new(vx, vy)),
+            ("disk", &[speed]) if speed >= 0.0 => VelocityDistribution::Disk(speed),
+            ("gaussian", &[sigma]) if sigma >= 0.0 => VelocityDistribution::Gaussian(sigma),
+            ("thermal", &[t]) if t >= 0.0 => VelocityDistribution::Thermal(t),
+            _ => {
+                return Err(self.error(
+                    key,
+                    "must be `vx vy`, `zero`, `disk speed`, `gaussian sigma` or `thermal temperature`",
+                ));
+            }
+        };
+        Ok(Some(distribution))
+    }
+
+    fn group(&self, boundary: Rectangle) -> Result<ParticleGroup, String> {
+        let missing = |key: &str| format!("line {}: group is missing {}", self.line, key);
+
+        let region = self.rectangle("region")?.unwrap_or(boundary);
+        let count = self.count("count")?.ok_or_else(|| missing("count"))?;
+        let radius = self.number("radius")?.ok_or_else(|| missing("radius"))?;
+        let mass = self.number("mass")?;
+        let color = self.color("color")?.unwrap_or(GREEN);
+        let velocity = self
+            .velocity("velocity")?
+            .unwrap_or(VelocityDistribution::Constant(Vector2::ZERO));
+        let track = match self.words("track") {
+            Some(words) => words
+                .iter()
+                .map(|w| w.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| self.error("track", "must be particle indices within the group"))?,
+            None => Vec::new(),
+        };
+        self.check_unused(&[
+            "region", "count", "radius", "mass", "color", "velocity", "track",
+        ])?;
+
+        if radius <= 0.0 {
+            return Err(self.error("radius", "must be positive"));
+        }
+        if mass.is_some_and(|m| m <= 0.0) {
+            return Err(self.error("mass", "must be positive"));
+        }
+        if !boundary.contains(region.min) || !boundary.contains(region.max) {
+            return Err(self.error("region", "must lie inside the boundary"));
+        }
+        if region.width() < 2.0 * radius || region.height() < 2.0 * radius {
+            return Err(self.error("region", "is smaller than a particle"));
+        }
+        if let Some(&index) = track.iter().find(|&&i| i >= count) {
+            return Err(self.error(
+                "track",
+                &format!("index {} is out of range for {} particles", index, count),
+            ));
+        }
+
+        Ok(ParticleGroup {
+            line: self.line,
+            region,
+            count,
+            radius,
+            mass,
+            color,
+            velocity,
+            track,
+        })
+    }
+}
@@ -1,56 +1,354 @@
 use std::collections::HashMap;
 
+use crate::constraint;
+use crate::constraint::Constraint;
 use crate::core::Particle;
 use crate::core::ParticleCollision;
+use crate::core::ParticleId;
+use crate::core::ParticleIds;
 use crate::core::Rectangle;
 use crate::core::StaticCollision;
+use crate::emitter::{Emitter, Sink};
+use crate::force_field::ForceField;
+use crate::parallel;
+use crate::particle_store::ParticleStore;
+use crate::rigid_cluster::RigidCluster;
+use crate::rng::SimRng;
+use crate::sleep;
+use crate::sleep::SleepSettings;
+use crate::spatial_order;
+use crate::uniform_grid::UniformGrid;
 use crate::vector2::Vector2;
 use crate::vector2::dot;
-use macroquad::prelude::*;
+
+// Contacts approaching slower than this many steps worth of the external
+// acceleration of their particles are treated as resting.
+const RESTING_CONTACT_STEPS: f64 = 2.0;
+const CONTACT_ITERATIONS: usize = 8;
+// Overlap tolerated between resting particles, relative to the sum of their radii.
+const RESTING_SLOP: f64 = 0.01;
+// Fraction of the remaining overlap of resting contacts that is removed per step.
+const RESTING_CORRECTION: f64 = 0.2;
 
 #[derive(Default)]
 pub struct Simulation {
-    pub window_width: f32,
-    pub window_height: f32,
     pub view: Rectangle,
-    pub particles: Vec<Particle>,
+    pub(crate) particles: ParticleStore, // only add_particle inserts, so every particle has an id
+    pub(crate) ids: ParticleIds,
     pub boundary: Rectangle,
     pub gravity: Vector2,
     pub restitution: f64,
-    pub trails: HashMap<usize, Vec<Vector2>>,
+    pub trails: HashMap<ParticleId, Vec<Vector2>>,
+    pub sleep: Option<SleepSettings>,
+    pub resting_contacts: bool, // solve contacts iteratively and let slow ones rest, for piles
+    pub constraints: Vec<Constraint>,
+    pub clusters: Vec<RigidCluster>,
+    pub emitters: Vec<Emitter>,
+    pub sinks: Vec<Sink>,
+    pub force_fields: Vec<Box<dyn ForceField>>,
+    pub time: f64,               // simulated time, passed to the force fields
+    pub threads: usize, // threads for integration and collision detection, 0 and 1 run serially
+    pub reorder_interval: usize, // steps between sorting the particles by cell, 0 never sorts
+    pub steps: u64,     // number of updates so far
+    pub rng: SimRng,    // used by the emitters, created from the scenario seed
+    pub wall_impulse: f64, // total momentum the walls took up so far, for the pressure
 }
 
 impl Simulation {
     pub fn update(&mut self, dt: f64) {
-        // apply gravity and integrate positions
-        for s in &mut self.particles {
-            s.velocity += self.gravity * dt;
-            s.position += s.velocity * dt;
+        self.emit_and_absorb(dt);
+
+        if self.reorder_interval > 0 && self.steps.is_multiple_of(self.reorder_interval as u64) {
+            self.reorder_particles();
         }
+
+        constraint::apply_spring_forces(&mut self.particles, &self.ids, &self.constraints, dt);
+
+        // apply gravity and external forces and integrate positions
+        let (gravity, time, fields) = (self.gravity, self.time, &self.force_fields);
+        let ParticleStore {
+            positions,
+            velocities,
+            masses,
+            asleep,
+            ..
+        } = &mut self.particles;
+        let accelerations: Vec<f64> =
+            parallel::map_chunks_mut(self.threads, positions, velocities, |start, xs, vs| {
+                let mut accelerations = vec![0.0; xs.len()];
+                for (k, (x, v)) in xs.iter_mut().zip(vs).enumerate() {
+                    let index = start + k;
+                    if asleep[index] {
+                        continue;
+                    }
+                    let force = fields
+                        .iter()
+                        .fold(Vector2::ZERO, |acc, f| acc + f.force(*x, *v, time));
+                    let acceleration = gravity + force * (1.0 / masses[index]);
+                    accelerations[k] = acceleration.length();
+                    *v += acceleration * dt;
+                    *x += *v * dt;
+                }
+                accelerations
+            })
+            .into_iter()
+            .flatten()
+            .collect();
+
+        for cluster in &mut self.clusters {
+            cluster.collect(&self.particles);
+        }
+
         // detect collisions
-        let p_collisions = detect_particle_collissions(&self.particles);
-        let s_collisions = detect_static_collissions(&self.particles, &self.boundary);
+        let p_collisions =
+            detect_particle_collissions(&self.particles, &self.boundary, self.threads);
+        let s_collisions = detect_static_collissions(&self.particles, &self.boundary, self.threads);
+
+        if let Some(settings) = &self.sleep {
+            sleep::wake_on_impact(&mut self.particles, &p_collisions, settings);
+        }
 
         // resolve collisions
-        resolve_particle_collisions(&mut self.particles, &p_collisions, self.restitution);
-        resolve_static_collisions(&mut self.particles, &s_collisions, self.restitution);
+        let iterative = self.resting_contacts;
+        let resting_speeds: Vec<f64> = accelerations
+            .iter()
+            .map(|&a| {
+                if iterative {
+                    RESTING_CONTACT_STEPS * a * dt
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        self.wall_impulse += if iterative {
+            solve_contacts_iteratively(
+                &mut self.particles,
+                &mut self.clusters,
+                &p_collisions,
+                &s_collisions,
+                self.restitution,
+                &resting_speeds,
+            )
+        } else {
+            let clusters = &mut self.clusters;
+            resolve_particle_collisions(
+                &mut self.particles,
+                clusters,
+                &p_collisions,
+                self.restitution,
+            );
+            resolve_static_collisions(
+                &mut self.particles,
+                clusters,
+                &s_collisions,
+                self.restitution,
+            )
+        };
+
+        // correct positions, the iterative solver already leaves the velocities
+        // consistent and clusters take their velocity from the momentum of all members
+        for c in &s_collisions {
+            self.particles.positions[c.index] += c.normal * c.penetration;
+            if !iterative && self.particles.clusters[c.index].is_none() {
+                self.particles.velocities[c.index] -= (c.normal * c.penetration) / dt;
+                // the wall takes this momentum too, so the pressure doesn't depend on the solver
+                self.wall_impulse -= self.particles.masses[c.index] * c.penetration / dt;
+            }
+        }
 
-        // correct positions
-        for c in s_collisions {
-            let p = &mut self.particles[c.index];
-            p.position += c.normal * c.penetration;
-            p.velocity -= (c.normal * c.penetration) / dt;
+        correct_particle_positions(
+            &mut self.particles,
+            &p_collisions,
+            dt,
+            &resting_speeds,
+            iterative,
+        );
+
+        constraint::solve_distance_constraints(&mut self.particles, &self.ids, &self.constraints);
+
+        for cluster in &mut self.clusters {
+            cluster.update(&mut self.particles);
         }
 
-        correct_particle_positions(&mut self.particles, &p_collisions, dt);
+        if let Some(settings) = &self.sleep {
+            sleep::update_sleep(
+                &mut self.particles,
+                &p_collisions,
+                &s_collisions,
+                settings,
+                dt,
+            );
+        }
 
         self.update_trails();
+        self.time += dt;
+        self.steps += 1;
+    }
+
+    /// Turns the given particles into a rigid cluster and returns its index.
+    /// Fails if a particle is not in the simulation or already in a cluster.
+    pub fn add_cluster(&mut self, members: &[ParticleId]) -> Result<usize, String> {
+        if members.is_empty() {
+            return Err("a cluster needs at least one particle".to_string());
+        }
+        let index = self.clusters.len();
+        let members = members
+            .iter()
+            .map(|&id| match self.ids.index_of(id) {
+                Some(i) if self.particles.clusters[i].is_some() => {
+                    Err(format!("particle {} is already in a cluster", id.0))
+                }
+                Some(i) => Ok(i),
+                None => Err(format!("particle {} is not in the simulation", id.0)),
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+        for &i in &members {
+            self.particles.clusters[i] = Some(index);
+        }
+        self.clusters
+            .push(RigidCluster::new(members, &self.particles));
+        Ok(index)
+    }
+
+    /// Appends a particle and returns its new id. Existing indices stay valid.
+    pub fn add_particle(&mut self, mut particle: Particle) -> ParticleId {
+        particle.id = self.ids.allocate(self.particles.len());
+        self.particles.push(particle);
+        particle.id
+    }
+
+    pub fn add_particles(&mut self, particles: Vec<Particle>) -> Vec<ParticleId> {
+        particles
+            .into_iter()
+            .map(|p| self.add_particle(p))
+            .collect()
+    }
+
+    pub fn particles(&self) -> &ParticleStore {
+        &self.particles
+    }
+
+    pub fn index_of(&self, id: ParticleId) -> Option<usize> {
+        self.ids.index_of(id)
+    }
+
+    pub fn particle(&self, id: ParticleId) -> Option<Particle> {
+        self.ids.index_of(id).map(|i| self.particles.get(i))
+    }
+
+    /// Starts recording the trail of a particle.
+    pub fn track(&mut self, id: ParticleId) {
+        self.trails.entry(id).or_default();
+    }
+
+    /// Removes the particles for which `remove` returns true. The order of the
+    /// remaining particles is kept and trails, constraints and clusters are
+    /// moved along to the new indices.
+    pub fn remove_particles<F>(&mut self, mut remove: F)
+    where
+        F: FnMut(&Particle) -> bool,
+    {
+        let keep: Vec<bool> = self.particles.iter().map(|p| !remove(&p)).collect();
+        self.retain_particles(&keep);
+    }
+
+    fn retain_particles(&mut self, keep: &[bool]) {
+        let mut remap = Vec::with_capacity(keep.len());
+        let mut next = 0;
+        for &k in keep {
+            if k {
+                remap.push(Some(next));
+                next += 1;
+            } else {
+                remap.push(None);
+            }
+        }
+        if next == keep.len() {
+            return;
+        }
+
+        if self.sleep.is_some() {
+            let removed: Vec<usize> = (0..keep.len()).filter(|&i| !keep[i]).collect();
+            sleep::wake_neighbours(&mut self.particles, self.boundary, &removed);
+        }
+        self.particles.retain(keep);
+        self.remap_indices(&remap);
+    }
+
+    /// Sorts the particles by grid cell so that neighbours in space are
+    /// neighbours in memory. Ids, trails, constraints and clusters follow.
+    pub fn reorder_particles(&mut self) {
+        let order = spatial_order::morton_order(&self.particles, self.boundary);
+        let mut remap = vec![None; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            remap[old] = Some(new);
+        }
+        self.particles.permute(&order);
+        self.remap_indices(&remap);
+    }
+
+    /// Moves everything that refers to particles by index to the new indices,
+    /// `remap[old]` is `None` for particles that are gone.
+    fn remap_indices(&mut self, remap: &[Option<usize>]) {
+        self.ids.rebuild(&self.particles.ids);
+        let ids = &self.ids;
+        self.trails.retain(|&id, _| ids.contains(id));
+
+        self.constraints.retain(|c| {
+            let (a, b) = c.particles();
+            ids.contains(a) && ids.contains(b)
+        });
+
+        // clusters that lost members are rebuilt from the rest, empty ones are dropped
+        let mut cluster_remap = Vec::with_capacity(self.clusters.len());
+        let mut clusters = Vec::with_capacity(self.clusters.len());
+        for cluster in std::mem::take(&mut self.clusters) {
+            let members: Vec<usize> = cluster.members.iter().filter_map(|&i| remap[i]).collect();
+            if members.is_empty() {
+                cluster_remap.push(None);
+                continue;
+            }
+            cluster_remap.push(Some(clusters.len()));
+            if members.len() == cluster.members.len() {
+                let mut cluster = cluster;
+                cluster.members = members;
+                clusters.push(cluster);
+            } else {
+                clusters.push(RigidCluster::new(members, &self.particles));
+            }
+        }
+        for cluster in &mut self.particles.clusters {
+            *cluster = cluster.and_then(|c| cluster_remap[c]);
+        }
+        self.clusters = clusters;
+    }
+
+    fn emit_and_absorb(&mut self, dt: f64) {
+        if !self.sinks.is_empty() {
+            let keep: Vec<bool> = self
+                .particles
+                .positions
+                .iter()
+                .map(|&x| !self.sinks.iter().any(|s| s.absorbs(x)))
+                .collect();
+            self.retain_particles(&keep);
+        }
+        for i in 0..self.emitters.len() {
+            let emitted = self.emitters[i].emit(dt, &self.particles, &mut self.rng);
+            self.add_particles(emitted);
+        }
+    }
+
+    pub fn sleeping_count(&self) -> usize {
+        self.particles.asleep.iter().filter(|&&a| a).count()
     }
 
     fn update_trails(&mut self) {
-        for (index, trail) in &mut self.trails {
-            let p = self.particles[*index];
-            trail.push(p.position);
+        for (id, trail) in &mut self.trails {
+            let Some(index) = self.ids.index_of(*id) else {
+                continue;
+            };
+            trail.push(self.particles.positions[index]);
             if trail.len() > 500 {
                 trail.remove(0);
             }
@@ -58,166 +356,358 @@ impl Simulation {
     }
 }
 
-fn detect_particle_collissions(particles: &Vec<Particle>) -> Vec<ParticleCollision> {
-    let mut collisions = Vec::new();
+/// Broadphase on a uniform grid. Every particle only looks at neighbours with a
+/// higher index in ascending order, so the collisions come out in the same order
+/// for any number of threads.
+fn detect_particle_collissions(
+    particles: &ParticleStore,
+    boundary: &Rectangle,
+    threads: usize,
+) -> Vec<ParticleCollision> {
+    let grid = UniformGrid::from_store(*boundary, particles);
+    let ParticleStore {
+        positions,
+        velocities,
+        radii,
+        asleep,
+        clusters,
+        ..
+    } = particles;
 
-    for i in 0..particles.len() {
-        for j in i + 1..particles.len() {
-            let p1 = particles[i];
-            let p2 = particles[j];
-            let n = p1.position - p2.position;
-            let d = n.length();
-            if d <= p1.radius + p2.radius {
-                collisions.push(ParticleCollision {
-                    i,
-                    j,
-                    normal: n.normalized(),
-                    penetration: p1.radius + p2.radius - d,
-                    velocity1: p1.velocity,
-                    velocity2: p2.velocity,
-                });
+    parallel::map_ranges(threads, particles.len(), |range| {
+        let mut collisions = Vec::new();
+        let mut neighbours = Vec::new();
+        for i in range {
+            grid.collect_close_colliders(positions[i], &mut neighbours);
+            neighbours.retain(|&j| j > i);
+            neighbours.sort_unstable();
+
+            for &j in &neighbours {
+                if asleep[i] && asleep[j] {
+                    continue;
+                }
+                // members of a rigid cluster may overlap each other
+                if clusters[i].is_some() && clusters[i] == clusters[j] {
+                    continue;
+                }
+                let n = positions[i] - positions[j];
+                let d = n.length();
+                let radius_sum = radii[i] + radii[j];
+                if d <= radius_sum {
+                    collisions.push(ParticleCollision {
+                        i,
+                        j,
+                        normal: n.normalized(),
+                        penetration: radius_sum - d,
+                        velocity1: velocities[i],
+                        velocity2: velocities[j],
+                    });
+                }
             }
         }
-    }
-    return collisions;
+        collisions
+    })
+    .into_iter()
+    .flatten()
+    .collect()
 }
 
 fn resolve_particle_collisions(
-    particles: &mut [Particle],
+    particles: &mut ParticleStore,
+    clusters: &mut [RigidCluster],
     collisions: &[ParticleCollision],
     restitution: f64,
 ) {
     for coll in collisions {
-        let (i, j) = (coll.i, coll.j);
-        unsafe {
-            let p1 = particles.get_unchecked_mut(i) as *mut Particle;
-            let p2 = particles.get_unchecked_mut(j) as *mut Particle;
-            add_impulse(&mut *p1, &mut *p2, coll, restitution);
-        }
+        add_impulse(particles, clusters, coll, restitution);
     }
 }
 
 fn add_impulse(
-    p1: &mut Particle,
-    p2: &mut Particle,
+    particles: &mut ParticleStore,
+    clusters: &mut [RigidCluster],
     collision: &ParticleCollision,
     restitution: f64,
 ) {
-    let n = (p2.position - p1.position).normalized();
-    // velocity from p1 relative to p2 (p2 is a fixed point)
-    let rel = collision.velocity1 - collision.velocity2;
-    //let rel = p1.velocity - p2.velocity;
+    let (i, j) = (collision.i, collision.j);
+    let n = (particles.positions[j] - particles.positions[i]).normalized();
+    // velocity from p1 relative to p2 (p2 is a fixed point), cluster members
+    // already move with the impulses of earlier contacts on their body
+    let rel = if particles.clusters[i].is_some() || particles.clusters[j].is_some() {
+        particles.velocities[i] - particles.velocities[j]
+    } else {
+        collision.velocity1 - collision.velocity2
+    };
     let vel_along = dot(rel, n);
     if vel_along <= 0.0 {
         // moving away from p2
         return;
     }
 
-    let mi = p1.mass;
-    let mj = p2.mass;
-    let mu = mi * mj / (mi + mj);
+    let inv_mi = inverse_mass_along(particles, clusters, i, n);
+    let inv_mj = inverse_mass_along(particles, clusters, j, n);
+    let mu = 1.0 / (inv_mi + inv_mj);
 
     let j_impulse = (1.0 + restitution) * mu * vel_along;
 
-    p1.velocity -= n * (j_impulse / mi);
-    p2.velocity += n * (j_impulse / mj);
+    apply_impulse(particles, clusters, i, -n * j_impulse);
+    apply_impulse(particles, clusters, j, n * j_impulse);
+}
+
+/// Inverse mass of particle `index` for an impulse along `normal`: zero while
+/// asleep and the effective inverse mass of the whole body for cluster members.
+fn inverse_mass_along(
+    particles: &ParticleStore,
+    clusters: &[RigidCluster],
+    index: usize,
+    normal: Vector2,
+) -> f64 {
+    match particles.clusters[index] {
+        Some(c) if !particles.asleep[index] => {
+            clusters[c].inverse_mass_at(particles.positions[index], normal)
+        }
+        _ => particles.inverse_mass(index),
+    }
+}
+
+/// Applies `impulse` to a free particle, or to the cluster it belongs to.
+fn apply_impulse(
+    particles: &mut ParticleStore,
+    clusters: &mut [RigidCluster],
+    index: usize,
+    impulse: Vector2,
+) {
+    if particles.asleep[index] {
+        return;
+    }
+    match particles.clusters[index] {
+        Some(c) => {
+            let point = particles.positions[index];
+            clusters[c].apply_impulse(particles, point, impulse);
+        }
+        None => {
+            let inv_m = particles.inverse_mass(index);
+            particles.velocities[index] += impulse * inv_m;
+        }
+    }
+}
+
+// Contacts that approach slower than a few steps of gravity are resting and
+// don't bounce, otherwise stacked particles jitter forever. Without gravity or
+// external forces nothing rests.
+fn is_resting(approach_speed: f64, resting_speed: f64) -> bool {
+    resting_speed > 0.0 && approach_speed < resting_speed
+}
+
+/// Resting speed of a contact between two particles, from the larger of their
+/// own accelerations.
+fn contact_resting_speed(resting_speeds: &[f64], i: usize, j: usize) -> f64 {
+    resting_speeds[i].max(resting_speeds[j])
+}
+
+fn separation_target(approach_speed: f64, restitution: f64, resting_speed: f64) -> f64 {
+    if is_resting(approach_speed, resting_speed) {
+        0.0
+    } else {
+        restitution * approach_speed
+    }
+}
+
+/// Sequential impulse solver for piles under gravity or external forces. A single pass over the contacts
+/// can't carry the weight of a pile down to the floor, so all contacts are
+/// solved together with the current velocities and clamped accumulated impulses.
+/// Returns the impulse transferred to the walls.
+fn solve_contacts_iteratively(
+    particles: &mut ParticleStore,
+    clusters: &mut [RigidCluster],
+    p_collisions: &[ParticleCollision],
+    s_collisions: &[StaticCollision],
+    restitution: f64,
+    resting_speeds: &[f64],
+) -> f64 {
+    let mut p_impulses = vec![0.0; p_collisions.len()];
+    let mut s_impulses = vec![0.0; s_collisions.len()];
+
+    for _ in 0..CONTACT_ITERATIONS {
+        for (coll, accumulated) in p_collisions.iter().zip(&mut p_impulses) {
+            let (i, j) = (coll.i, coll.j);
+            let inv_m1 = inverse_mass_along(particles, clusters, i, coll.normal);
+            let inv_m2 = inverse_mass_along(particles, clusters, j, coll.normal);
+            let resting_speed = contact_resting_speed(resting_speeds, i, j);
+            let target = separation_target(coll.approach_speed(), restitution, resting_speed);
+            let separation_speed = dot(
+                particles.velocities[i] - particles.velocities[j],
+                coll.normal,
+            );
+
+            let new_impulse =
+                (*accumulated + (target - separation_speed) / (inv_m1 + inv_m2)).max(0.0);
+            let impulse = new_impulse - *accumulated;
+            *accumulated = new_impulse;
+
+            apply_impulse(particles, clusters, i, coll.normal * impulse);
+            apply_impulse(particles, clusters, j, -coll.normal * impulse);
+        }
+        for (c, accumulated) in s_collisions.iter().zip(&mut s_impulses) {
+            let inv_m = inverse_mass_along(particles, clusters, c.index, c.normal);
+            let resting_speed = resting_speeds[c.index];
+            let target = separation_target(c.approach_speed(), restitution, resting_speed);
+            let separation_speed = dot(c.normal, particles.velocities[c.index]);
+
+            let new_impulse = (*accumulated + (target - separation_speed) / inv_m).max(0.0);
+            let impulse = new_impulse - *accumulated;
+            *accumulated = new_impulse;
+
+            apply_impulse(particles, clusters, c.index, c.normal * impulse);
+        }
+    }
+
+    s_impulses.iter().sum()
 }
 
-fn detect_static_collissions(particles: &[Particle], boundary: &Rectangle) -> Vec<StaticCollision> {
+fn detect_static_collissions(
+    particles: &ParticleStore,
+    boundary: &Rectangle,
+    threads: usize,
+) -> Vec<StaticCollision> {
+    parallel::map_ranges(threads, particles.len(), |range| {
+        detect_static_collissions_in(particles, range, boundary)
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn detect_static_collissions_in(
+    particles: &ParticleStore,
+    range: std::ops::Range<usize>,
+    boundary: &Rectangle,
+) -> Vec<StaticCollision> {
     let mut collisions = Vec::new();
 
-    for (index, p) in particles.iter().enumerate() {
+    for index in range {
+        if particles.asleep[index] {
+            continue;
+        }
+        let (position, radius) = (particles.positions[index], particles.radii[index]);
+        let velocity = particles.velocities[index];
         // top
-        if p.position.y + p.radius > boundary.max.y {
+        if position.y + radius > boundary.max.y {
             collisions.push(StaticCollision {
                 index,
                 normal: Vector2::new(0.0, -1.0),
-                penetration: (p.position.y + p.radius) - boundary.max.y,
-                velocity: p.velocity,
+                penetration: (position.y + radius) - boundary.max.y,
+                velocity,
             });
         }
 
         // right
-        if p.position.x + p.radius > boundary.max.x {
+        if position.x + radius > boundary.max.x {
             collisions.push(StaticCollision {
                 index,
                 normal: Vector2::new(-1.0, 0.0),
-                penetration: (p.position.x + p.radius) - boundary.max.x,
-                velocity: p.velocity,
+                penetration: (position.x + radius) - boundary.max.x,
+                velocity,
             });
         }
 
         // bottom
-        if p.position.y - p.radius < boundary.min.y {
+        if position.y - radius < boundary.min.y {
             let normal = Vector2::new(0.0, 1.0);
             collisions.push(StaticCollision {
                 index,
                 normal,
-                penetration: boundary.min.y - (p.position.y - p.radius),
-                velocity: p.velocity,
+                penetration: boundary.min.y - (position.y - radius),
+                velocity,
             });
         }
 
         // left
-        if p.position.x - p.radius < boundary.min.x {
+        if position.x - radius < boundary.min.x {
             collisions.push(StaticCollision {
                 index,
                 normal: Vector2::new(1.0, 0.0),
-                penetration: boundary.min.x - (p.position.x - p.radius),
-                velocity: p.velocity,
+                penetration: boundary.min.x - (position.x - radius),
+                velocity,
             });
         }
     }
-    return collisions;
+    collisions
 }
 
 // Restitution is a value from 0 to 1; 1 means perfectly elastic (no energy loss), 0 means perfectly inelastic.
+// Returns the impulse transferred to the walls.
 fn resolve_static_collisions(
-    particles: &mut [Particle],
+    particles: &mut ParticleStore,
+    clusters: &mut [RigidCluster],
     collisions: &[StaticCollision],
     restitution: f64,
-) {
+) -> f64 {
+    let mut wall_impulse = 0.0;
     for c in collisions {
-        let p = &mut particles[c.index];
+        if particles.clusters[c.index].is_some() {
+            // with the current velocity, earlier contacts may have stopped the body already
+            let approach_speed = -dot(c.normal, particles.velocities[c.index]);
+            if approach_speed > 0.0 {
+                let inv_m = inverse_mass_along(particles, clusters, c.index, c.normal);
+                let impulse = (1.0 + restitution) * approach_speed / inv_m;
+                apply_impulse(particles, clusters, c.index, c.normal * impulse);
+                wall_impulse += impulse;
+            }
+            continue;
+        }
         let n = dot(c.normal, c.velocity) * c.normal;
-        p.velocity -= (1.0 + restitution) * n;
+        particles.velocities[c.index] -= (1.0 + restitution) * n;
+        wall_impulse += particles.masses[c.index] * (1.0 + restitution) * n.length();
     }
+    wall_impulse
 }
 
 fn correct_particle_positions(
-    particles: &mut [Particle],
+    particles: &mut ParticleStore,
     collisions: &[ParticleCollision],
     dt: f64,
+    resting_speeds: &[f64],
+    iterative: bool,
 ) {
     for coll in collisions {
-        // get two mutable refs safely
-        let (i, j) = if coll.i < coll.j {
-            (coll.i, coll.j)
-        } else {
-            (coll.j, coll.i)
-        };
-        let (left, right) = particles.split_at_mut(j);
-        let p1 = &mut left[i];
-        let p2 = &mut right[0];
+        let (i, j) = (coll.i, coll.j);
 
         let normal = coll.normal;
-        let penetration = coll.penetration;
+        let resting_speed = contact_resting_speed(resting_speeds, i, j);
+        let resting = is_resting(coll.approach_speed(), resting_speed);
+        let penetration = if resting {
+            // pushing resting contacts fully apart makes particles with several
+            // contacts overshoot, so only part of the overlap is removed per step
+            let slop = RESTING_SLOP * (particles.radii[i] + particles.radii[j]);
+            (coll.penetration - slop).max(0.0) * RESTING_CORRECTION
+        } else {
+            coll.penetration
+        };
 
         if penetration <= 0.0 {
             continue;
         }
 
         // compute correction magnitude (reduced mass * penetration)
-        let inv_mass_sum = 1.0 / p1.mass + 1.0 / p2.mass;
+        let (inv_m1, inv_m2) = (particles.inverse_mass(i), particles.inverse_mass(j));
+        let inv_mass_sum = inv_m1 + inv_m2;
         let correction_mag = penetration / inv_mass_sum;
         let correction = normal * correction_mag;
 
         // apply
-        p1.position += correction * (1.0 / p1.mass);
-        p2.position -= correction * (1.0 / p2.mass);
+        particles.positions[i] += correction * inv_m1;
+        particles.positions[j] -= correction * inv_m2;
 
-        // correct velocities for energy balance
-        p1.velocity += correction / dt * (1.0 / p1.mass);
-        p2.velocity -= correction / dt * (1.0 / p2.mass);
+        // correct velocities for energy balance, the iterative solver doesn't need it
+        if iterative {
+            continue;
+        }
+        if particles.clusters[i].is_none() {
+            particles.velocities[i] += correction / dt * inv_m1;
+        }
+        if particles.clusters[j].is_none() {
+            particles.velocities[j] -= correction / dt * inv_m2;
+        }
     }
 }
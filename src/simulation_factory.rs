@@ -1,46 +1,194 @@
-use std::collections::HashMap;
-
-use macroquad::color::Color;
-use rand_pcg::Pcg64Mcg;
-
 use crate::{
-    core::{Particle, Rectangle},
+    color::{CRIMSON, GREEN},
+    constraint::Constraint,
+    core::{Particle, ParticleId, Rectangle},
+    emitter::{Emitter, Sink, SpeedDistribution},
+    force_field::{LinearDrag, RadialAttractor, TimeVarying, Vortex, WindZone},
+    rng::{DEFAULT_SEED, SimRng},
     simulation::Simulation,
+    sleep::SleepSettings,
     vector2::Vector2,
 };
 
-const GREEN: Color = Color::new(0.0, 0.8667, 0.8353, 1.0);
-const RED: Color = Color::new(0.9254, 0.0745, 0.2745, 1.0);
+/// Overrides for the defaults of a scenario, `None` keeps the scenario's value.
+#[derive(Debug, Clone, Copy)]
+pub struct ScenarioParams {
+    pub seed: u64,
+    pub count: Option<usize>,
+    pub radius: Option<f64>,
+    pub temperature: Option<f64>,
+    pub gravity: Option<f64>, // vertical component, negative pulls down
+    pub restitution: Option<f64>,
+}
+
+impl Default for ScenarioParams {
+    fn default() -> Self {
+        ScenarioParams {
+            seed: DEFAULT_SEED,
+            count: None,
+            radius: None,
+            temperature: None,
+            gravity: None,
+            restitution: None,
+        }
+    }
+}
+
+pub struct Scenario {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub uses: &'static [&'static str], // which of count, radius and temperature the scenario reads
+    create: fn(&ScenarioParams) -> Result<Simulation, String>,
+}
+
+impl Scenario {
+    /// Creates the simulation, gravity and restitution apply to every scenario.
+    /// Fails if the particles don't fit, e.g. for a too large radius.
+    pub fn build(&self, params: &ScenarioParams) -> Result<Simulation, String> {
+        let mut sim = (self.create)(params)?;
+        if let Some(gravity) = params.gravity {
+            sim.gravity = Vector2::new(0.0, gravity);
+        }
+        if let Some(restitution) = params.restitution {
+            sim.restitution = restitution;
+        }
+        Ok(sim)
+    }
+}
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "brownian",
+        description: "big particle kicked around by a gas of small ones",
+        uses: &["count", "radius", "temperature"],
+        create: brownian_motion_sim,
+    },
+    Scenario {
+        name: "collision",
+        description: "elastic disks under weak gravity",
+        uses: &["count", "radius"],
+        create: collision_sim,
+    },
+    Scenario {
+        name: "sandpile",
+        description: "inelastic grains settling into a pile",
+        uses: &["count", "radius"],
+        create: sandpile_sim,
+    },
+    Scenario {
+        name: "mixing",
+        description: "two gases mixing in a tall box",
+        uses: &["count", "radius"],
+        create: mixing_sim,
+    },
+    Scenario {
+        name: "chain",
+        description: "hanging chain and a soft ring",
+        uses: &[],
+        create: chain_sim,
+    },
+    Scenario {
+        name: "fountain",
+        description: "two nozzles spraying into a basin with a drain",
+        uses: &["radius"],
+        create: fountain_sim,
+    },
+    Scenario {
+        name: "vortex",
+        description: "particles in a vortex stirred by a gust",
+        uses: &["count", "radius"],
+        create: vortex_sim,
+    },
+    Scenario {
+        name: "cluster-dimer",
+        description: "brownian motion of a rigid dimer",
+        uses: &["count", "radius", "temperature"],
+        create: |params| brownian_cluster_sim(ClusterShape::Dimer, params),
+    },
+    Scenario {
+        name: "cluster-trimer",
+        description: "brownian motion of a rigid trimer",
+        uses: &["count", "radius", "temperature"],
+        create: |params| brownian_cluster_sim(ClusterShape::Trimer, params),
+    },
+    Scenario {
+        name: "cluster-l",
+        description: "brownian motion of a rigid L shape",
+        uses: &["count", "radius", "temperature"],
+        create: |params| brownian_cluster_sim(ClusterShape::LShape, params),
+    },
+];
 
-pub fn collision_sim() -> Simulation {
-    const RADIUS: f64 = 0.02;
+pub fn find_scenario(name: &str) -> Option<&'static Scenario> {
+    SCENARIOS.iter().find(|s| s.name == name)
+}
+
+pub fn collision_sim(params: &ScenarioParams) -> Result<Simulation, String> {
+    let radius = params.radius.unwrap_or(0.02);
+    let count = params.count.unwrap_or(100);
 
     let boundary = Rectangle {
         min: Vector2 { x: 0.0, y: 0.0 },
         max: Vector2 { x: 2.0, y: 1.0 },
     };
 
-    let mut particles = generate_non_overlapping_particles(boundary, RADIUS, 100, 5);
+    let mut rng = SimRng::new(params.seed);
+    let mut particles = generate_non_overlapping_particles(&mut rng, boundary, radius, count, 5)?;
     for p in &mut particles {
-        p.mass = std::f64::consts::PI * RADIUS * RADIUS;
-        p.velocity = Vector2::random_in_disk() * 0.5;
+        p.mass = std::f64::consts::PI * radius * radius;
+        p.velocity = Vector2::random_in_disk(&mut rng) * 0.5;
     }
 
-    Simulation {
-        window_width: 800.0,
-        window_height: 400.0,
-        particles,
+    let mut sim = Simulation {
         view: boundary,
         boundary,
         gravity: Vector2 { x: 0.0, y: -0.1 },
         restitution: 1.0,
+        rng,
         ..Default::default()
+    };
+    sim.add_particles(particles);
+    Ok(sim)
+}
+
+pub fn sandpile_sim(params: &ScenarioParams) -> Result<Simulation, String> {
+    let radius = params.radius.unwrap_or(0.01);
+    let count = params.count.unwrap_or(400);
+
+    let boundary = Rectangle {
+        min: Vector2 { x: 0.0, y: 0.0 },
+        max: Vector2 { x: 1.0, y: 1.0 },
+    };
+
+    let spawn_region = Rectangle {
+        min: Vector2 { x: 0.2, y: 0.3 },
+        max: Vector2 { x: 0.8, y: 1.0 },
+    };
+
+    let mut rng = SimRng::new(params.seed);
+    let mut particles =
+        generate_non_overlapping_particles(&mut rng, spawn_region, radius, count, 100)?;
+    for p in &mut particles {
+        p.velocity = Vector2::random_in_disk(&mut rng) * 0.1;
     }
+
+    let mut sim = Simulation {
+        view: boundary,
+        boundary,
+        gravity: Vector2 { x: 0.0, y: -1.0 },
+        restitution: 0.3,
+        sleep: Some(SleepSettings::default()),
+        resting_contacts: true,
+        rng,
+        ..Default::default()
+    };
+    sim.add_particles(particles);
+    Ok(sim)
 }
 
-pub fn mixing_sim() -> Simulation {
-    const RADIUS: f64 = 0.01;
-    const COUNT: usize = 300;
+pub fn mixing_sim(params: &ScenarioParams) -> Result<Simulation, String> {
+    let radius = params.radius.unwrap_or(0.01);
+    let count = params.count.unwrap_or(300);
 
     let boundary = Rectangle {
         min: Vector2 { x: 0.0, y: 0.0 },
@@ -52,7 +200,9 @@ pub fn mixing_sim() -> Simulation {
         max: Vector2 { x: 1.0, y: 2.0 },
     };
 
-    let top_particles = generate_non_overlapping_particles(top_boundary, RADIUS, COUNT, 5);
+    let mut rng = SimRng::new(params.seed);
+    let top_particles =
+        generate_non_overlapping_particles(&mut rng, top_boundary, radius, count, 5)?;
 
     let bottom_boundary = Rectangle {
         min: Vector2 { x: 0.0, y: 0.0 },
@@ -60,36 +210,223 @@ pub fn mixing_sim() -> Simulation {
     };
 
     let mut bottom_particles =
-        generate_non_overlapping_particles(bottom_boundary, RADIUS, COUNT, 5);
+        generate_non_overlapping_particles(&mut rng, bottom_boundary, radius, count, 5)?;
     for p in &mut bottom_particles {
-        p.color = RED;
+        p.color = CRIMSON;
     }
 
     let mut particles = top_particles;
     particles.extend(bottom_particles);
 
     for p in &mut particles {
-        p.mass = std::f64::consts::PI * RADIUS * RADIUS;
-        p.velocity = Vector2::random_in_disk() * 0.5;
+        p.mass = std::f64::consts::PI * radius * radius;
+        p.velocity = Vector2::random_in_disk(&mut rng) * 0.5;
     }
 
-    Simulation {
-        window_width: 400.0,
-        window_height: 800.0,
-        particles,
+    let mut sim = Simulation {
         view: boundary,
         boundary,
         gravity: Vector2 { x: 0.0, y: -0.0 },
         restitution: 1.0,
+        rng,
+        ..Default::default()
+    };
+    sim.add_particles(particles);
+    Ok(sim)
+}
+
+pub fn chain_sim(params: &ScenarioParams) -> Result<Simulation, String> {
+    const RADIUS: f64 = 0.01;
+    const MASS: f64 = 1.0;
+    const CHAIN_LENGTH: usize = 40;
+    const BODY_RADIUS: f64 = 0.08;
+    const BODY_SEGMENTS: usize = 16;
+
+    let boundary = Rectangle {
+        min: Vector2 { x: 0.0, y: 0.0 },
+        max: Vector2 { x: 1.0, y: 1.0 },
+    };
+
+    let mut sim = Simulation {
+        view: boundary,
+        boundary,
+        gravity: Vector2 { x: 0.0, y: -1.0 },
+        restitution: 0.5,
+        rng: SimRng::new(params.seed),
+        ..Default::default()
+    };
+    let mut constraints = Vec::new();
+
+    // polymer chain of rigid links
+    let spacing = 2.5 * RADIUS;
+    let mut previous = None;
+    for k in 0..CHAIN_LENGTH {
+        let id = sim.add_particle(Particle {
+            mass: MASS,
+            position: Vector2::new(0.1 + k as f64 * spacing, 0.8),
+            radius: RADIUS,
+            color: GREEN,
+            ..Default::default()
+        });
+        if let Some(previous) = previous {
+            constraints.push(Constraint::distance(previous, id, spacing));
+        }
+        previous = Some(id);
+    }
+
+    // soft body, a ring of disks held together by springs to its neighbours and the center
+    let center = Vector2::new(0.5, 0.4);
+    let center_id = sim.add_particle(Particle {
+        mass: MASS,
+        position: center,
+        radius: RADIUS,
+        color: CRIMSON,
+        ..Default::default()
+    });
+    let ring: Vec<ParticleId> = (0..BODY_SEGMENTS)
+        .map(|k| {
+            let angle = 2.0 * std::f64::consts::PI * k as f64 / BODY_SEGMENTS as f64;
+            sim.add_particle(Particle {
+                mass: MASS,
+                position: center + BODY_RADIUS * Vector2::new(angle.cos(), angle.sin()),
+                radius: RADIUS,
+                color: CRIMSON,
+                ..Default::default()
+            })
+        })
+        .collect();
+    let segment_length = 2.0 * BODY_RADIUS * (std::f64::consts::PI / BODY_SEGMENTS as f64).sin();
+    for k in 0..BODY_SEGMENTS {
+        let next = ring[(k + 1) % BODY_SEGMENTS];
+        constraints.push(Constraint::spring(
+            ring[k],
+            next,
+            segment_length,
+            2000.0,
+            5.0,
+        ));
+        constraints.push(Constraint::spring(
+            center_id,
+            ring[k],
+            BODY_RADIUS,
+            500.0,
+            2.0,
+        ));
+    }
+
+    sim.constraints = constraints;
+    Ok(sim)
+}
+
+/// Two nozzles spray into a basin with a drain in the middle of the floor.
+pub fn fountain_sim(params: &ScenarioParams) -> Result<Simulation, String> {
+    let radius = params.radius.unwrap_or(0.01);
+
+    let boundary = Rectangle {
+        min: Vector2 { x: 0.0, y: 0.0 },
+        max: Vector2 { x: 1.0, y: 1.0 },
+    };
+
+    let speed = SpeedDistribution::gaussian(1.2, 0.1).expect("valid nozzle speed");
+    let emitters = vec![
+        Emitter::new(
+            Vector2::new(0.05, 0.3),
+            Vector2::new(1.0, 1.0),
+            40.0,
+            speed,
+            radius,
+            GREEN,
+        )
+        .with_spread(0.1),
+        Emitter::new(
+            Vector2::new(0.95, 0.3),
+            Vector2::new(-1.0, 1.0),
+            40.0,
+            speed,
+            radius,
+            CRIMSON,
+        )
+        .with_spread(0.1),
+    ];
+
+    let sinks = vec![Sink {
+        region: Rectangle {
+            min: Vector2 { x: 0.45, y: 0.0 },
+            max: Vector2 { x: 0.55, y: 0.03 },
+        },
+    }];
+
+    Ok(Simulation {
+        view: boundary,
+        boundary,
+        gravity: Vector2 { x: 0.0, y: -1.0 },
+        restitution: 0.3,
+        sleep: Some(SleepSettings::default()),
+        resting_contacts: true,
+        emitters,
+        sinks,
+        rng: SimRng::new(params.seed),
         ..Default::default()
+    })
+}
+
+/// Particles swirling in a vortex with drag, stirred by a gust that changes direction.
+pub fn vortex_sim(params: &ScenarioParams) -> Result<Simulation, String> {
+    let radius = params.radius.unwrap_or(0.01);
+    let count = params.count.unwrap_or(300);
+
+    let boundary = Rectangle {
+        min: Vector2 { x: 0.0, y: 0.0 },
+        max: Vector2 { x: 1.0, y: 1.0 },
+    };
+    let center = (boundary.min + boundary.max) / 2.0;
+
+    let mut rng = SimRng::new(params.seed);
+    let mut particles = generate_non_overlapping_particles(&mut rng, boundary, radius, count, 10)?;
+    for p in &mut particles {
+        p.mass = 1.0;
     }
+
+    let gust = WindZone {
+        region: Rectangle {
+            min: Vector2 { x: 0.0, y: 0.8 },
+            max: Vector2 { x: 1.0, y: 1.0 },
+        },
+        velocity: Vector2 { x: 1.0, y: 0.0 },
+        coefficient: 2.0,
+    };
+
+    let mut sim = Simulation {
+        view: boundary,
+        boundary,
+        restitution: 0.8,
+        force_fields: vec![
+            Box::new(Vortex {
+                center,
+                strength: 0.3,
+                core_radius: 0.05,
+            }),
+            Box::new(RadialAttractor {
+                center,
+                strength: 0.01,
+                softening: 0.05,
+            }),
+            Box::new(LinearDrag { coefficient: 0.5 }),
+            Box::new(TimeVarying::oscillating(gust, 4.0)),
+        ],
+        rng,
+        ..Default::default()
+    };
+    let ids = sim.add_particles(particles);
+    sim.track(ids[0]);
+    Ok(sim)
 }
 
-pub fn brownian_motion_sim() -> Simulation {
-    const RADIUS: f64 = 0.005;
-    const BIG_RADIUS: f64 = RADIUS * 10.0;
+pub fn brownian_motion_sim(params: &ScenarioParams) -> Result<Simulation, String> {
+    let radius = params.radius.unwrap_or(0.005);
+    let big_radius = radius * 10.0;
     const MASS: f64 = 1.0;
-    const COUNT: usize = 1000;
+    let count = params.count.unwrap_or(1000);
 
     let boundary = Rectangle {
         min: Vector2 { x: 0.0, y: 0.0 },
@@ -97,16 +434,17 @@ pub fn brownian_motion_sim() -> Simulation {
     };
 
     // Generate small particles using generate_non_overlapping_particles
-    let mut particles = generate_non_overlapping_particles(boundary, RADIUS, COUNT, 10);
+    let mut rng = SimRng::new(params.seed);
+    let mut particles = generate_non_overlapping_particles(&mut rng, boundary, radius, count, 10)?;
 
     // Set masses and velocities for small particles
     const KB: f64 = 1.0; // Boltzmann constant, normalized
-    const T: f64 = 1.0; // arbitrary temperature
-    let sigma = (KB * T / MASS).sqrt();
+    let temperature = params.temperature.unwrap_or(1.0);
+    let sigma = (KB * temperature / MASS).sqrt();
 
     for p in &mut particles {
         p.mass = MASS;
-        p.velocity = Vector2::random_gaussian(0.0, sigma);
+        p.velocity = Vector2::random_gaussian(&mut rng, 0.0, sigma);
     }
 
     // Generate big particle, ensuring no overlap with small particles
@@ -114,48 +452,151 @@ pub fn brownian_motion_sim() -> Simulation {
         mass: MASS * 100.0,
         position: Vector2::ZERO, // Will be set
         velocity: Vector2::ZERO,
-        radius: BIG_RADIUS,
-        color: RED,
+        radius: big_radius,
+        color: CRIMSON,
+        ..Default::default()
     };
 
     // Create a grid for overlap checking with the big particle
-    let mut grid = crate::uniform_grid::UniformGrid::with_cell_size(boundary, 2.0 * BIG_RADIUS);
+    let mut grid = crate::uniform_grid::UniformGrid::with_cell_size(boundary, 2.0 * big_radius);
     for (i, p) in particles.iter().enumerate() {
-        grid.add_particle(i, p);
+        grid.add_particle(i, p.position);
     }
 
-    match grid.try_get_none_overlaping_position(big_p.radius, &particles, 1000) {
+    match grid.try_get_none_overlaping_position(&mut rng, big_p.radius, &particles, 1000) {
         Ok(position) => {
             big_p.position = position;
             particles.push(big_p);
         }
-        Err(_) => {
-            panic!("Error: Could not place big particle without overlapping small particles.");
+        Err(err) => return Err(format!("could not place the big particle: {}", err)),
+    }
+
+    let mut sim = Simulation {
+        view: boundary,
+        boundary,
+        gravity: Vector2::ZERO,
+        restitution: 1.0,
+        reorder_interval: 100,
+        rng,
+        ..Default::default()
+    };
+    let ids = sim.add_particles(particles);
+    sim.track(ids[ids.len() - 1]);
+    Ok(sim)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ClusterShape {
+    Dimer,
+    Trimer,
+    LShape,
+}
+
+impl ClusterShape {
+    /// Disk centers of the shape for disks of the given radius, touching each other.
+    fn offsets(self, radius: f64) -> Vec<Vector2> {
+        let d = 2.0 * radius;
+        match self {
+            ClusterShape::Dimer => vec![Vector2::new(-0.5 * d, 0.0), Vector2::new(0.5 * d, 0.0)],
+            ClusterShape::Trimer => {
+                let h = d * 3.0_f64.sqrt() / 2.0;
+                vec![
+                    Vector2::new(-0.5 * d, 0.0),
+                    Vector2::new(0.5 * d, 0.0),
+                    Vector2::new(0.0, h),
+                ]
+            }
+            ClusterShape::LShape => vec![
+                Vector2::new(0.0, 2.0 * d),
+                Vector2::new(0.0, d),
+                Vector2::new(0.0, 0.0),
+                Vector2::new(d, 0.0),
+            ],
         }
     }
+}
+
+/// Brownian motion of a non-spherical tracer, a rigid cluster of big disks in a gas of small ones.
+pub fn brownian_cluster_sim(
+    shape: ClusterShape,
+    params: &ScenarioParams,
+) -> Result<Simulation, String> {
+    let radius = params.radius.unwrap_or(0.005);
+    let big_radius = radius * 6.0;
+    const MASS: f64 = 1.0;
+    let count = params.count.unwrap_or(1000);
+
+    let boundary = Rectangle {
+        min: Vector2 { x: 0.0, y: 0.0 },
+        max: Vector2 { x: 2.0, y: 1.0 },
+    };
+
+    let mut rng = SimRng::new(params.seed);
+    let mut particles = generate_non_overlapping_particles(&mut rng, boundary, radius, count, 10)?;
+
+    const KB: f64 = 1.0; // Boltzmann constant, normalized
+    let temperature = params.temperature.unwrap_or(1.0);
+    let sigma = (KB * temperature / MASS).sqrt();
+
+    for p in &mut particles {
+        p.mass = MASS;
+        p.velocity = Vector2::random_gaussian(&mut rng, 0.0, sigma);
+    }
+
+    // place the cluster in the middle and make room for it
+    let center = (boundary.min + boundary.max) / 2.0;
+    let disks: Vec<Particle> = shape
+        .offsets(big_radius)
+        .into_iter()
+        .map(|offset| Particle {
+            mass: MASS * 50.0,
+            position: center + offset,
+            radius: big_radius,
+            color: CRIMSON,
+            ..Default::default()
+        })
+        .collect();
+    particles.retain(|p| {
+        disks
+            .iter()
+            .all(|d| (p.position - d.position).length() > p.radius + d.radius)
+    });
 
-    let mut trails: HashMap<usize, Vec<Vector2>> = HashMap::new();
-    trails.insert(particles.len() - 1, Vec::new());
+    let first_member = particles.len();
+    particles.extend(disks);
 
-    Simulation {
-        window_width: 500.0,
-        window_height: 500.0,
-        particles,
+    let mut sim = Simulation {
         view: boundary,
         boundary,
         gravity: Vector2::ZERO,
         restitution: 1.0,
-        trails,
+        reorder_interval: 100,
+        rng,
         ..Default::default()
-    }
+    };
+    let ids = sim.add_particles(particles);
+    sim.track(ids[first_member]);
+    sim.add_cluster(&ids[first_member..])?;
+    Ok(sim)
 }
 
+/// Places up to `count` particles, fewer with a warning if the region gets
+/// crowded. Fails if a single particle doesn't fit into the region.
 fn generate_non_overlapping_particles(
+    rng: &mut SimRng,
     boundary: Rectangle,
     particle_radius: f64,
     count: usize,
     max_attempts_per_particle: usize,
-) -> Vec<Particle> {
+) -> Result<Vec<Particle>, String> {
+    if 2.0 * particle_radius >= boundary.width().min(boundary.height()) {
+        return Err(format!(
+            "radius {} is too large for the {} x {} region of the particles",
+            particle_radius,
+            boundary.width(),
+            boundary.height()
+        ));
+    }
     let mut particles = Vec::with_capacity(count);
     let mut grid =
         crate::uniform_grid::UniformGrid::with_cell_size(boundary, 2.0 * particle_radius);
@@ -167,9 +608,11 @@ fn generate_non_overlapping_particles(
             velocity: Vector2::ZERO,
             mass: std::f64::consts::PI * particle_radius * particle_radius,
             color: GREEN,
+            ..Default::default()
         };
 
         match grid.try_get_none_overlaping_position(
+            rng,
             particle.radius,
             &particles,
             max_attempts_per_particle,
@@ -177,7 +620,7 @@ fn generate_non_overlapping_particles(
             Ok(position) => {
                 particle.position = position;
                 particles.push(particle);
-                grid.add_particle(particles.len() - 1, &particle);
+                grid.add_particle(particles.len() - 1, particle.position);
             }
             Err(err) => {
                 eprintln!(
@@ -191,5 +634,5 @@ fn generate_non_overlapping_particles(
         }
     }
 
-    particles
+    Ok(particles)
 }
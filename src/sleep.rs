@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use crate::core::ParticleCollision;
+use crate::core::Rectangle;
+use crate::core::StaticCollision;
+use crate::particle_store::ParticleStore;
+use crate::uniform_grid::UniformGrid;
+use crate::vector2::Vector2;
+use crate::vector2::dot;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SleepSettings {
+    pub velocity_threshold: f64, // speed below which a particle counts as resting
+    pub time_to_sleep: f64,      // how long a whole island has to rest before it sleeps
+    pub wake_threshold: f64,     // impact speed that wakes a sleeping particle
+}
+
+impl Default for SleepSettings {
+    fn default() -> Self {
+        SleepSettings {
+            velocity_threshold: 0.01,
+            time_to_sleep: 0.5,
+            wake_threshold: 0.05,
+        }
+    }
+}
+
+/// Wakes sleeping particles that are hit hard enough by an awake one or
+/// touch one that moves faster than the wake threshold.
+pub fn wake_on_impact(
+    particles: &mut ParticleStore,
+    collisions: &[ParticleCollision],
+    settings: &SleepSettings,
+) {
+    for c in collisions {
+        if particles.asleep[c.i] == particles.asleep[c.j] {
+            continue;
+        }
+        let (sleeper, mover) = if particles.asleep[c.i] {
+            (c.i, c.j)
+        } else {
+            (c.j, c.i)
+        };
+        let impact = dot(c.velocity2 - c.velocity1, c.normal).abs();
+        let speed = particles.velocities[mover].length();
+        if impact > settings.wake_threshold || speed > settings.wake_threshold {
+            wake(particles, sleeper);
+        }
+    }
+}
+
+/// Wakes sleeping particles that touch one of `removed`, before those are
+/// taken out and stop supporting them.
+pub fn wake_neighbours(particles: &mut ParticleStore, boundary: Rectangle, removed: &[usize]) {
+    if removed.is_empty() || !particles.asleep.contains(&true) {
+        return;
+    }
+    let grid = UniformGrid::from_store(boundary, particles);
+    let mut neighbours = Vec::new();
+    for &r in removed {
+        grid.collect_close_colliders(particles.positions[r], &mut neighbours);
+        for &i in &neighbours {
+            let distance = (particles.positions[i] - particles.positions[r]).length();
+            if particles.asleep[i] && distance <= particles.radii[i] + particles.radii[r] {
+                wake(particles, i);
+            }
+        }
+    }
+}
+
+/// Puts islands of touching, slow particles to sleep once they rest on
+/// something.
+///
+/// An island only contains awake particles; sleeping particles behave like
+/// static ground and don't keep resting particles on top of them awake. Rest
+/// time only counts while one particle of the island touches a wall or a
+/// sleeping particle, so slow particles in free space stay awake. The members
+/// of a rigid cluster form one island and only sleep together.
+pub fn update_sleep(
+    particles: &mut ParticleStore,
+    p_collisions: &[ParticleCollision],
+    s_collisions: &[StaticCollision],
+    settings: &SleepSettings,
+    dt: f64,
+) {
+    let mut islands = DisjointSet::new(particles.len());
+    let mut cluster_members = HashMap::new();
+    for index in 0..particles.len() {
+        if let Some(c) = particles.clusters[index] {
+            let first = *cluster_members.entry(c).or_insert(index);
+            islands.union(index, first);
+        }
+    }
+    let mut supported = vec![false; particles.len()];
+    for c in s_collisions {
+        supported[c.index] = true;
+    }
+    for c in p_collisions {
+        match (particles.asleep[c.i], particles.asleep[c.j]) {
+            (false, false) => islands.union(c.i, c.j),
+            (true, false) => supported[c.j] = true,
+            (false, true) => supported[c.i] = true,
+            (true, true) => {}
+        }
+    }
+
+    let mut island_supported = vec![false; particles.len()];
+    for index in 0..particles.len() {
+        if !particles.asleep[index] && supported[index] {
+            island_supported[islands.find(index)] = true;
+        }
+    }
+
+    let mut island_rest_time = vec![f64::INFINITY; particles.len()];
+    for index in 0..particles.len() {
+        if particles.asleep[index] {
+            continue;
+        }
+        let root = islands.find(index);
+        let sleep_time = &mut particles.sleep_times[index];
+        if island_supported[root]
+            && particles.velocities[index].length() < settings.velocity_threshold
+        {
+            *sleep_time += dt;
+        } else {
+            *sleep_time = 0.0;
+        }
+        island_rest_time[root] = island_rest_time[root].min(*sleep_time);
+    }
+
+    for index in 0..particles.len() {
+        let root = islands.find(index);
+        if !particles.asleep[index] && island_rest_time[root] >= settings.time_to_sleep {
+            particles.asleep[index] = true;
+            particles.velocities[index] = Vector2::ZERO;
+        }
+    }
+}
+
+pub fn wake(particles: &mut ParticleStore, index: usize) {
+    particles.asleep[index] = false;
+    particles.sleep_times[index] = 0.0;
+}
+
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> DisjointSet {
+        DisjointSet {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra != rb {
+            self.parent[ra] = rb;
+        }
+    }
+}
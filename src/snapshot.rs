@@ -0,0 +1,772 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::constraint::Constraint;
+use crate::core::ParticleId;
+use crate::core::ParticleIds;
+use crate::core::Rectangle;
+use crate::particle_store::ParticleStore;
+use crate::rigid_cluster::RigidCluster;
+use crate::rng::SimRng;
+use crate::simulation::Simulation;
+use crate::sleep::SleepSettings;
+use crate::vector2::Vector2;
+
+const MAGIC: &[u8; 8] = b"SFSNAP\0\0";
+const TEXT_HEADER: &str = "simple_fluids_snapshot";
+const VERSION: u64 = 3;
+
+/// The evolving state of a simulation, to pause a run and resume it later.
+///
+/// Force fields are closures and can't be saved, so a snapshot is applied to
+/// a simulation freshly built from the same scenario, which brings the force
+/// fields, emitters and sinks. Everything that changes during a run is
+/// replaced, so stepping the restored simulation gives bit-for-bit the same
+/// results as the uninterrupted run.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub time: f64,
+    pub steps: u64,
+    pub view: Rectangle,
+    pub boundary: Rectangle,
+    pub gravity: Vector2,
+    pub restitution: f64,
+    pub sleep: Option<SleepSettings>,
+    pub resting_contacts: bool,
+    pub reorder_interval: usize,
+    pub rng: SimRng,
+    pub next_id: ParticleId,
+    pub particles: ParticleStore,
+    pub trails: Vec<(ParticleId, Vec<Vector2>)>, // sorted by id
+    pub constraints: Vec<Constraint>,
+    pub clusters: Vec<RigidCluster>,
+    pub emitters_pending: Vec<f64>,
+    pub wall_impulse: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Binary,
+    Text,
+}
+
+impl Format {
+    /// Text for `.txt` files, binary for everything else.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("txt") => Format::Text,
+            _ => Format::Binary,
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn capture(sim: &Simulation) -> Snapshot {
+        let mut trails: Vec<_> = sim
+            .trails
+            .iter()
+            .map(|(&id, trail)| (id, trail.clone()))
+            .collect();
+        trails.sort_by_key(|&(id, _)| id);
+
+        Snapshot {
+            time: sim.time,
+            steps: sim.steps,
+            view: sim.view,
+            boundary: sim.boundary,
+            gravity: sim.gravity,
+            restitution: sim.restitution,
+            sleep: sim.sleep,
+            resting_contacts: sim.resting_contacts,
+            reorder_interval: sim.reorder_interval,
+            rng: sim.rng.clone(),
+            next_id: sim.ids.next(),
+            particles: sim.particles.clone(),
+            trails,
+            constraints: sim.constraints.clone(),
+            clusters: sim.clusters.clone(),
+            emitters_pending: sim.emitters.iter().map(|e| e.pending()).collect(),
+            wall_impulse: sim.wall_impulse,
+        }
+    }
+
+    /// Replaces the state of `sim`, which has to have the snapshot's emitters.
+    pub fn apply(&self, sim: &mut Simulation) -> io::Result<()> {
+        if sim.emitters.len() != self.emitters_pending.len() {
+            return Err(invalid_data(format!(
+                "snapshot has {} emitters but the scenario has {}",
+                self.emitters_pending.len(),
+                sim.emitters.len()
+            )));
+        }
+
+        sim.time = self.time;
+        sim.steps = self.steps;
+        sim.view = self.view;
+        sim.boundary = self.boundary;
+        sim.gravity = self.gravity;
+        sim.restitution = self.restitution;
+        sim.sleep = self.sleep;
+        sim.resting_contacts = self.resting_contacts;
+        sim.reorder_interval = self.reorder_interval;
+        sim.rng = self.rng.clone();
+        sim.ids = ParticleIds::restore(self.next_id.0, &self.particles.ids);
+        sim.particles = self.particles.clone();
+        sim.trails = self.trails.iter().cloned().collect();
+        sim.constraints = self.constraints.clone();
+        sim.clusters = self.clusters.clone();
+        for (emitter, &pending) in sim.emitters.iter_mut().zip(&self.emitters_pending) {
+            emitter.set_pending(pending);
+        }
+        sim.wall_impulse = self.wall_impulse;
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out, Format::from_path(path))?;
+        out.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Snapshot> {
+        let mut input = BufReader::new(File::open(path)?);
+        Snapshot::read(&mut input, Format::from_path(path))
+    }
+
+    pub fn write(&self, out: &mut impl Write, format: Format) -> io::Result<()> {
+        match format {
+            Format::Binary => {
+                out.write_all(MAGIC)?;
+                self.encode(&mut BinaryEncoder { out })
+            }
+            Format::Text => {
+                write!(out, "{}", TEXT_HEADER)?;
+                self.encode(&mut TextEncoder { out })?;
+                writeln!(out)
+            }
+        }
+    }
+
+    pub fn read(input: &mut impl Read, format: Format) -> io::Result<Snapshot> {
+        match format {
+            Format::Binary => {
+                let mut magic = [0; 8];
+                input.read_exact(&mut magic)?;
+                if &magic != MAGIC {
+                    return Err(invalid_data("not a binary snapshot".to_string()));
+                }
+                Snapshot::decode(&mut BinaryDecoder { input })
+            }
+            Format::Text => {
+                let mut text = String::new();
+                input.read_to_string(&mut text)?;
+                let mut decoder = TextDecoder {
+                    tokens: text.split_whitespace(),
+                    label: TEXT_HEADER,
+                };
+                if decoder.tokens.next() != Some(TEXT_HEADER) {
+                    return Err(invalid_data("not a text snapshot".to_string()));
+                }
+                Snapshot::decode(&mut decoder)
+            }
+        }
+    }
+
+    /// Single description of the layout, shared by the binary and text format.
+    fn encode(&self, e: &mut impl Encoder) -> io::Result<()> {
+        e.label("version")?;
+        e.u64(VERSION)?;
+        e.label("time")?;
+        e.f64(self.time)?;
+        e.label("steps")?;
+        e.u64(self.steps)?;
+        e.label("view")?;
+        e.rectangle(self.view)?;
+        e.label("boundary")?;
+        e.rectangle(self.boundary)?;
+        e.label("gravity")?;
+        e.vector(self.gravity)?;
+        e.label("restitution")?;
+        e.f64(self.restitution)?;
+        e.label("sleep")?;
+        e.bool(self.sleep.is_some())?;
+        let sleep = self.sleep.unwrap_or_default();
+        e.f64(sleep.velocity_threshold)?;
+        e.f64(sleep.time_to_sleep)?;
+        e.f64(sleep.wake_threshold)?;
+        e.label("reorder_interval")?;
+        e.u64(self.reorder_interval as u64)?;
+        e.label("rng")?;
+        e.u64(self.rng.seed())?;
+        e.u64(self.rng.stream())?;
+        e.u128(self.rng.draws())?;
+        e.label("next_id")?;
+        e.u64(self.next_id.0)?;
+
+        let p = &self.particles;
+        e.label("particles")?;
+        e.u64(p.len() as u64)?;
+        for i in 0..p.len() {
+            e.label("p")?;
+            e.u64(p.ids[i].0)?;
+            e.vector(p.positions[i])?;
+            e.vector(p.velocities[i])?;
+            e.f64(p.radii[i])?;
+            e.f64(p.masses[i])?;
+            e.color(p.colors[i])?;
+            e.bool(p.asleep[i])?;
+            e.f64(p.sleep_times[i])?;
+            e.index(p.clusters[i])?;
+        }
+
+        e.label("trails")?;
+        e.u64(self.trails.len() as u64)?;
+        for (id, trail) in &self.trails {
+            e.label("trail")?;
+            e.u64(id.0)?;
+            e.u64(trail.len() as u64)?;
+            for &point in trail {
+                e.vector(point)?;
+            }
+        }
+
+        // stored by index, links to particles that are gone are left out
+        let ids = ParticleIds::restore(self.next_id.0, &self.particles.ids);
+        let links: Vec<(&Constraint, (usize, usize))> = self
+            .constraints
+            .iter()
+            .filter_map(|c| Some((c, c.indices(&ids)?)))
+            .collect();
+        e.label("constraints")?;
+        e.u64(links.len() as u64)?;
+        for (c, (a, b)) in links {
+            match *c {
+                Constraint::Spring {
+                    rest_length,
+                    stiffness,
+                    damping,
+                    ..
+                } => {
+                    e.either("spring", "distance", true)?;
+                    e.u64(a as u64)?;
+                    e.u64(b as u64)?;
+                    e.f64(rest_length)?;
+                    e.f64(stiffness)?;
+                    e.f64(damping)?;
+                }
+                Constraint::Distance { length, .. } => {
+                    e.either("spring", "distance", false)?;
+                    e.u64(a as u64)?;
+                    e.u64(b as u64)?;
+                    e.f64(length)?;
+                }
+            }
+        }
+
+        e.label("clusters")?;
+        e.u64(self.clusters.len() as u64)?;
+        for c in &self.clusters {
+            e.label("cluster")?;
+            e.vector(c.center)?;
+            e.f64(c.angle)?;
+            e.vector(c.velocity)?;
+            e.f64(c.angular_velocity)?;
+            e.u64(c.members.len() as u64)?;
+            for (&member, &offset) in c.members.iter().zip(&c.body_offsets) {
+                e.u64(member as u64)?;
+                e.vector(offset)?;
+            }
+        }
+
+        e.label("emitters")?;
+        e.u64(self.emitters_pending.len() as u64)?;
+        for &pending in &self.emitters_pending {
+            e.f64(pending)?;
+        }
+
+        e.label("wall_impulse")?;
+        e.f64(self.wall_impulse)?;
+        e.label("resting_contacts")?;
+        e.bool(self.resting_contacts)
+    }
+
+    fn decode(d: &mut impl Decoder) -> io::Result<Snapshot> {
+        d.label("version")?;
+        let version = d.u64()?;
+        // version 1 lacks the wall impulse and version 2 the resting contacts at the end
+        if !(1..=VERSION).contains(&version) {
+            return Err(invalid_data(format!(
+                "snapshot version {} is not supported, expected 1 to {}",
+                version, VERSION
+            )));
+        }
+        d.label("time")?;
+        let time = d.f64()?;
+        d.label("steps")?;
+        let steps = d.u64()?;
+        d.label("view")?;
+        let view = d.rectangle()?;
+        d.label("boundary")?;
+        let boundary = d.rectangle()?;
+        d.label("gravity")?;
+        let gravity = d.vector()?;
+        d.label("restitution")?;
+        let restitution = d.f64()?;
+        d.label("sleep")?;
+        let has_sleep = d.bool()?;
+        let sleep = SleepSettings {
+            velocity_threshold: d.f64()?,
+            time_to_sleep: d.f64()?,
+            wake_threshold: d.f64()?,
+        };
+        d.label("reorder_interval")?;
+        let reorder_interval = d.count()?;
+        d.label("rng")?;
+        let rng = SimRng::restore(d.u64()?, d.u64()?, d.u128()?);
+        d.label("next_id")?;
+        let next_id = ParticleId(d.u64()?);
+
+        d.label("particles")?;
+        let count = d.count()?;
+        let mut particles = ParticleStore::default();
+        for _ in 0..count {
+            d.label("p")?;
+            particles.ids.push(ParticleId(d.u64()?));
+            particles.positions.push(d.vector()?);
+            particles.velocities.push(d.vector()?);
+            particles.radii.push(d.f64()?);
+            particles.masses.push(d.f64()?);
+            particles.colors.push(d.color()?);
+            particles.asleep.push(d.bool()?);
+            particles.sleep_times.push(d.f64()?);
+            particles.clusters.push(d.index()?);
+        }
+        // ids must stay unique and below the next one handed out
+        let mut seen = HashSet::with_capacity(count);
+        for &id in &particles.ids {
+            if !seen.insert(id) {
+                return Err(invalid_data(format!("particle id {} appears twice", id.0)));
+            }
+            if id >= next_id {
+                return Err(invalid_data(format!(
+                    "particle id {} is not below next_id {}",
+                    id.0, next_id.0
+                )));
+            }
+        }
+        let particle_index = |index: u64| {
+            if index < count as u64 {
+                Ok(index as usize)
+            } else {
+                Err(invalid_data(format!(
+                    "particle index {} out of range",
+                    index
+                )))
+            }
+        };
+
+        d.label("trails")?;
+        let mut trails = Vec::new();
+        for _ in 0..d.count()? {
+            d.label("trail")?;
+            let id = ParticleId(d.u64()?);
+            let trail = (0..d.count()?)
+                .map(|_| d.vector())
+                .collect::<io::Result<Vec<_>>>()?;
+            trails.push((id, trail));
+        }
+
+        d.label("constraints")?;
+        let mut constraints = Vec::new();
+        for _ in 0..d.count()? {
+            let constraint = match d.either("spring", "distance")? {
+                true => Constraint::Spring {
+                    a: particles.ids[particle_index(d.u64()?)?],
+                    b: particles.ids[particle_index(d.u64()?)?],
+                    rest_length: d.f64()?,
+                    stiffness: d.f64()?,
+                    damping: d.f64()?,
+                },
+                false => Constraint::Distance {
+                    a: particles.ids[particle_index(d.u64()?)?],
+                    b: particles.ids[particle_index(d.u64()?)?],
+                    length: d.f64()?,
+                },
+            };
+            constraints.push(constraint);
+        }
+
+        d.label("clusters")?;
+        let mut clusters = Vec::new();
+        for _ in 0..d.count()? {
+            d.label("cluster")?;
+            let center = d.vector()?;
+            let angle = d.f64()?;
+            let velocity = d.vector()?;
+            let angular_velocity = d.f64()?;
+            let mut members = Vec::new();
+            let mut body_offsets = Vec::new();
+            for _ in 0..d.count()? {
+                let member = particle_index(d.u64()?)?;
+                if particles.clusters[member] != Some(clusters.len()) {
+                    return Err(invalid_data(format!(
+                        "particle {} is a member of cluster {} but not marked as one",
+                        member,
+                        clusters.len()
+                    )));
+                }
+                members.push(member);
+                body_offsets.push(d.vector()?);
+            }
+            if members.is_empty() {
+                return Err(invalid_data(format!(
+                    "cluster {} has no members",
+                    clusters.len()
+                )));
+            }
+            let mut cluster = RigidCluster::from_body(members, body_offsets, &particles);
+            cluster.center = center;
+            cluster.angle = angle;
+            cluster.velocity = velocity;
+            cluster.angular_velocity = angular_velocity;
+            clusters.push(cluster);
+        }
+        for (index, &cluster) in particles.clusters.iter().enumerate() {
+            if let Some(c) = cluster
+                && c >= clusters.len()
+            {
+                return Err(invalid_data(format!(
+                    "particle {} is in cluster {} of {}",
+                    index,
+                    c,
+                    clusters.len()
+                )));
+            }
+        }
+
+        d.label("emitters")?;
+        let emitters_pending = (0..d.count()?)
+            .map(|_| d.f64())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let wall_impulse = if version >= 2 {
+            d.label("wall_impulse")?;
+            d.f64()?
+        } else {
+            0.0
+        };
+        // older versions solved contacts iteratively in the scenarios with sleep
+        let resting_contacts = if version >= 3 {
+            d.label("resting_contacts")?;
+            d.bool()?
+        } else {
+            has_sleep
+        };
+
+        Ok(Snapshot {
+            time,
+            steps,
+            view,
+            boundary,
+            gravity,
+            restitution,
+            sleep: has_sleep.then_some(sleep),
+            resting_contacts,
+            reorder_interval,
+            rng,
+            next_id,
+            particles,
+            trails,
+            constraints,
+            clusters,
+            emitters_pending,
+            wall_impulse,
+        })
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Writes values in order. Labels only show up in the text format.
+trait Encoder {
+    fn label(&mut self, label: &str) -> io::Result<()>;
+    fn u64(&mut self, x: u64) -> io::Result<()>;
+    fn u128(&mut self, x: u128) -> io::Result<()>;
+    fn f64(&mut self, x: f64) -> io::Result<()>;
+    fn f32(&mut self, x: f32) -> io::Result<()>;
+
+    /// Writes one of two labels, the binary format stores a flag instead.
+    fn either(&mut self, first: &str, second: &str, is_first: bool) -> io::Result<()> {
+        self.label(if is_first { first } else { second })
+    }
+
+    fn bool(&mut self, x: bool) -> io::Result<()> {
+        self.u64(x as u64)
+    }
+
+    fn index(&mut self, index: Option<usize>) -> io::Result<()> {
+        self.u64(index.map_or(u64::MAX, |i| i as u64))
+    }
+
+    fn vector(&mut self, v: Vector2) -> io::Result<()> {
+        self.f64(v.x)?;
+        self.f64(v.y)
+    }
+
+    fn rectangle(&mut self, r: Rectangle) -> io::Result<()> {
+        self.vector(r.min)?;
+        self.vector(r.max)
+    }
+
+    fn color(&mut self, c: Color) -> io::Result<()> {
+        self.f32(c.r)?;
+        self.f32(c.g)?;
+        self.f32(c.b)?;
+        self.f32(c.a)
+    }
+}
+
+/// Reads the values written by an `Encoder` in the same order.
+trait Decoder {
+    fn label(&mut self, label: &'static str) -> io::Result<()>;
+    /// Reads one of two labels, true for the first.
+    fn either(&mut self, first: &'static str, second: &'static str) -> io::Result<bool>;
+    fn u64(&mut self) -> io::Result<u64>;
+    fn u128(&mut self) -> io::Result<u128>;
+    fn f64(&mut self) -> io::Result<f64>;
+    fn f32(&mut self) -> io::Result<f32>;
+
+    fn bool(&mut self) -> io::Result<bool> {
+        match self.u64()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            x => Err(invalid_data(format!("expected 0 or 1, found {}", x))),
+        }
+    }
+
+    fn index(&mut self) -> io::Result<Option<usize>> {
+        let index = self.u64()?;
+        Ok((index != u64::MAX).then_some(index as usize))
+    }
+
+    /// A length, checked against a sane limit before anything is allocated.
+    fn count(&mut self) -> io::Result<usize> {
+        const MAX_COUNT: u64 = 1 << 32;
+        match self.u64()? {
+            x if x <= MAX_COUNT => Ok(x as usize),
+            x => Err(invalid_data(format!("count {} is too large", x))),
+        }
+    }
+
+    fn vector(&mut self) -> io::Result<Vector2> {
+        Ok(Vector2::new(self.f64()?, self.f64()?))
+    }
+
+    fn rectangle(&mut self) -> io::Result<Rectangle> {
+        Ok(Rectangle {
+            min: self.vector()?,
+            max: self.vector()?,
+        })
+    }
+
+    fn color(&mut self) -> io::Result<Color> {
+        Ok(Color::new(
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+        ))
+    }
+}
+
+/// Little endian, floats as their raw bits.
+struct BinaryEncoder<'a, W: Write> {
+    out: &'a mut W,
+}
+
+impl<W: Write> Encoder for BinaryEncoder<'_, W> {
+    fn label(&mut self, _label: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn either(&mut self, _first: &str, _second: &str, is_first: bool) -> io::Result<()> {
+        self.bool(is_first)
+    }
+
+    fn u64(&mut self, x: u64) -> io::Result<()> {
+        self.out.write_all(&x.to_le_bytes())
+    }
+
+    fn u128(&mut self, x: u128) -> io::Result<()> {
+        self.out.write_all(&x.to_le_bytes())
+    }
+
+    fn f64(&mut self, x: f64) -> io::Result<()> {
+        self.out.write_all(&x.to_le_bytes())
+    }
+
+    fn f32(&mut self, x: f32) -> io::Result<()> {
+        self.out.write_all(&x.to_le_bytes())
+    }
+}
+
+struct BinaryDecoder<'a, R: Read> {
+    input: &'a mut R,
+}
+
+impl<R: Read> BinaryDecoder<'_, R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.input.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl<R: Read> Decoder for BinaryDecoder<'_, R> {
+    fn label(&mut self, _label: &'static str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn either(&mut self, _first: &'static str, _second: &'static str) -> io::Result<bool> {
+        self.bool()
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn u128(&mut self) -> io::Result<u128> {
+        Ok(u128::from_le_bytes(self.bytes()?))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.bytes()?))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.bytes()?))
+    }
+}
+
+/// One labelled record per line. Floats are printed with the shortest
+/// representation that parses back to the same bits.
+struct TextEncoder<'a, W: Write> {
+    out: &'a mut W,
+}
+
+impl<W: Write> Encoder for TextEncoder<'_, W> {
+    fn label(&mut self, label: &str) -> io::Result<()> {
+        write!(self.out, "\n{}", label)
+    }
+
+    fn u64(&mut self, x: u64) -> io::Result<()> {
+        write!(self.out, " {}", x)
+    }
+
+    fn u128(&mut self, x: u128) -> io::Result<()> {
+        write!(self.out, " {}", x)
+    }
+
+    fn f64(&mut self, x: f64) -> io::Result<()> {
+        write!(self.out, " {:?}", x)
+    }
+
+    fn f32(&mut self, x: f32) -> io::Result<()> {
+        write!(self.out, " {:?}", x)
+    }
+
+    fn index(&mut self, index: Option<usize>) -> io::Result<()> {
+        match index {
+            Some(i) => write!(self.out, " {}", i),
+            None => write!(self.out, " -"),
+        }
+    }
+}
+
+struct TextDecoder<'a> {
+    tokens: std::str::SplitWhitespace<'a>,
+    label: &'static str, // last label read, for error messages
+}
+
+impl TextDecoder<'_> {
+    fn parse<T: std::str::FromStr>(&mut self) -> io::Result<T> {
+        let label = self.label;
+        let token = self
+            .tokens
+            .next()
+            .ok_or_else(|| invalid_data(format!("snapshot ends in {}", label)))?;
+        token
+            .parse()
+            .map_err(|_| invalid_data(format!("invalid value `{}` in {}", token, label)))
+    }
+}
+
+impl Decoder for TextDecoder<'_> {
+    fn label(&mut self, label: &'static str) -> io::Result<()> {
+        match self.tokens.next() {
+            Some(token) if token == label => {
+                self.label = label;
+                Ok(())
+            }
+            token => Err(invalid_data(format!(
+                "expected {} after {}, found `{}`",
+                label,
+                self.label,
+                token.unwrap_or("end of file")
+            ))),
+        }
+    }
+
+    fn either(&mut self, first: &'static str, second: &'static str) -> io::Result<bool> {
+        match self.tokens.next() {
+            Some(token) if token == first => {
+                self.label = first;
+                Ok(true)
+            }
+            Some(token) if token == second => {
+                self.label = second;
+                Ok(false)
+            }
+            token => Err(invalid_data(format!(
+                "expected {} or {} after {}, found `{}`",
+                first,
+                second,
+                self.label,
+                token.unwrap_or("end of file")
+            ))),
+        }
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        self.parse()
+    }
+
+    fn u128(&mut self) -> io::Result<u128> {
+        self.parse()
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        self.parse()
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        self.parse()
+    }
+
+    fn index(&mut self) -> io::Result<Option<usize>> {
+        if self.tokens.clone().next() == Some("-") {
+            self.tokens.next();
+            return Ok(None);
+        }
+        self.parse().map(Some)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::core::Rectangle;
+use crate::particle_store::ParticleStore;
+use crate::uniform_grid::UniformGrid;
+
+/// Particle indices sorted along a Z-order curve through the broadphase cells.
+///
+/// Particles in the same or neighbouring cells end up close together in
+/// memory. The sort is stable, so the order is deterministic.
+pub fn morton_order(particles: &ParticleStore, boundary: Rectangle) -> Vec<usize> {
+    let grid = UniformGrid::new(boundary, particles.max_radius());
+    let mut order: Vec<usize> = (0..particles.len()).collect();
+    order.sort_by_cached_key(|&i| {
+        let (col, row) = grid.get_cell_indices(particles.positions[i]);
+        morton_key(col as u32, row as u32)
+    });
+    order
+}
+
+fn morton_key(col: u32, row: u32) -> u64 {
+    spread_bits(col) | (spread_bits(row) << 1)
+}
+
+// Moves bit k of the value to bit 2k.
+fn spread_bits(value: u32) -> u64 {
+    let mut x = value as u64;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555;
+    x
+}
@@ -0,0 +1,185 @@
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+use crate::color::BOUNDARY;
+use crate::color::Color;
+use crate::color::LETTERBOX;
+use crate::color::RED;
+use crate::color::WHITE;
+use crate::color::YELLOW;
+use crate::constraint::Constraint;
+use crate::headless::FrameSequence;
+use crate::headless::FrameWriter;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+use crate::viewport::Viewport;
+
+/// Writes what `viewport` shows of the simulation as an SVG image, laid out
+/// like the window: the boundary, constraints, particles as circles and
+/// trails as polylines, clipped to the view inside letterbox bars.
+pub fn write_svg(out: &mut impl Write, sim: &Simulation, viewport: Viewport) -> io::Result<()> {
+    let (width, height) = (viewport.width.round(), viewport.height.round());
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\">",
+        w = width,
+        h = height
+    )?;
+    writeln!(
+        out,
+        "<!-- simple_fluids time {} step {} seed {} stream {} -->",
+        sim.time,
+        sim.steps,
+        sim.rng.seed(),
+        sim.rng.stream()
+    )?;
+    // letterbox bars around the view, the content is clipped to it
+    let view = viewport.screen_rect();
+    let view_rect = format!(
+        "x=\"{:.3}\" y=\"{:.3}\" width=\"{:.3}\" height=\"{:.3}\"",
+        view.min.x,
+        view.min.y,
+        view.width(),
+        view.height()
+    );
+    writeln!(
+        out,
+        "<rect width=\"100%\" height=\"100%\" {}/>",
+        fill(LETTERBOX)
+    )?;
+    writeln!(out, "<rect {} fill=\"black\"/>", view_rect)?;
+    writeln!(
+        out,
+        "<defs><clipPath id=\"view\"><rect {}/></clipPath></defs>",
+        view_rect
+    )?;
+    writeln!(out, "<g clip-path=\"url(#view)\">")?;
+
+    let b = sim.boundary;
+    let top_left = viewport.to_screen(Vector2::new(b.min.x, b.max.y));
+    let bottom_right = viewport.to_screen(Vector2::new(b.max.x, b.min.y));
+    let size = bottom_right - top_left;
+    writeln!(
+        out,
+        "<rect x=\"{:.3}\" y=\"{:.3}\" width=\"{:.3}\" height=\"{:.3}\" fill=\"none\" {}/>",
+        top_left.x,
+        top_left.y,
+        size.x,
+        size.y,
+        stroke(BOUNDARY)
+    )?;
+
+    writeln!(out, "<g stroke-width=\"1\">")?;
+    for c in &sim.constraints {
+        let Some((i, j)) = c.indices(&sim.ids) else {
+            continue;
+        };
+        let color = match c {
+            Constraint::Spring { .. } => YELLOW,
+            Constraint::Distance { .. } => WHITE,
+        };
+        let a = viewport.to_screen(sim.particles.positions[i]);
+        let b = viewport.to_screen(sim.particles.positions[j]);
+        writeln!(
+            out,
+            "<line x1=\"{:.3}\" y1=\"{:.3}\" x2=\"{:.3}\" y2=\"{:.3}\" {}/>",
+            a.x,
+            a.y,
+            b.x,
+            b.y,
+            stroke(color)
+        )?;
+    }
+    writeln!(out, "</g>")?;
+
+    let scale = viewport.scale().x;
+    let p = &sim.particles;
+    writeln!(out, "<g>")?;
+    for i in 0..p.len() {
+        let center = viewport.to_screen(p.positions[i]);
+        writeln!(
+            out,
+            "<circle cx=\"{:.3}\" cy=\"{:.3}\" r=\"{:.3}\" {}/>",
+            center.x,
+            center.y,
+            scale * p.radii[i],
+            fill(p.colors[i])
+        )?;
+    }
+    writeln!(out, "</g>")?;
+
+    writeln!(out, "<g fill=\"none\" stroke-width=\"1\">")?;
+    for trail in sim.trails.values().filter(|trail| trail.len() > 1) {
+        write!(out, "<polyline {} points=\"", stroke(RED))?;
+        for (k, &point) in trail.iter().enumerate() {
+            let s = viewport.to_screen(point);
+            let separator = if k == 0 { "" } else { " " };
+            write!(out, "{}{:.3},{:.3}", separator, s.x, s.y)?;
+        }
+        writeln!(out, "\"/>")?;
+    }
+    writeln!(out, "</g>")?;
+    writeln!(out, "</g>")?;
+    writeln!(out, "</svg>")
+}
+
+pub fn save_svg(path: &Path, sim: &Simulation, viewport: Viewport) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_svg(&mut out, sim, viewport)?;
+    out.flush()
+}
+
+fn rgb(color: Color) -> String {
+    let [r, g, b] = [color.r, color.g, color.b].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn fill(color: Color) -> String {
+    paint("fill", color)
+}
+
+fn stroke(color: Color) -> String {
+    paint("stroke", color)
+}
+
+/// `fill` or `stroke` attributes, with an opacity only for translucent colors.
+fn paint(property: &str, color: Color) -> String {
+    if color.a < 1.0 {
+        format!(
+            "{p}=\"{}\" {p}-opacity=\"{}\"",
+            rgb(color),
+            color.a,
+            p = property
+        )
+    } else {
+        format!("{}=\"{}\"", property, rgb(color))
+    }
+}
+
+/// Writes SVG frames with a fixed viewport size.
+pub struct SvgFrames {
+    width: usize,
+    height: usize,
+}
+
+impl FrameWriter for SvgFrames {
+    const EXTENSION: &'static str = "svg";
+
+    fn write(&mut self, path: &Path, sim: &Simulation) -> io::Result<()> {
+        let viewport = Viewport::new(sim.view, self.width as f64, self.height as f64);
+        save_svg(path, sim, viewport)
+    }
+}
+
+/// Writes numbered SVG frames `base_00000.svg`, `base_00001.svg`, ...
+pub type SvgSequence = FrameSequence<SvgFrames>;
+
+impl SvgSequence {
+    pub fn create(base: &Path, width: usize, height: usize) -> io::Result<SvgSequence> {
+        FrameSequence::new(base, SvgFrames { width, height })
+    }
+}
@@ -1,12 +1,16 @@
-use std::collections::HashSet;
-
 use crate::{
     core::{Particle, Rectangle},
+    particle_store::ParticleStore,
+    rng::SimRng,
     vector2::Vector2,
 };
 
+/// Upper limit of the cell count, so tiny particles can't make the grid
+/// larger than the memory.
+const MAX_CELLS: usize = 1 << 20;
+
 pub struct UniformGrid {
-    cells: Vec<HashSet<usize>>,
+    cells: Vec<Vec<usize>>, // particle indices in insertion order
     cell_width: f64,
     cell_height: f64,
     boundary: Rectangle,
@@ -15,49 +19,38 @@ pub struct UniformGrid {
 }
 
 impl UniformGrid {
-    pub fn new(boundary: Rectangle, particles: &[Particle]) -> UniformGrid {
-        let width = boundary.width();
-        let height = boundary.height();
-
-        let max_radius = particles
-            .iter()
-            .map(|p| p.radius)
-            .max_by(|a, b| a.partial_cmp(b).unwrap());
-
-        let cell_size_min: f64 = match max_radius {
-            Some(r) => 2.0 * r,
-            None => width.min(height),
+    /// Grid with cells large enough that a particle of `max_radius` can only
+    /// touch particles in the neighbouring cells.
+    pub fn new(boundary: Rectangle, max_radius: f64) -> UniformGrid {
+        let cell_size = if max_radius > 0.0 {
+            2.0 * max_radius
+        } else {
+            boundary.width().min(boundary.height())
         };
-
-        let n_col = (width / cell_size_min).floor().max(1.0) as usize;
-        let n_row = (height / cell_size_min).floor().max(1.0) as usize;
-
-        let cell_width = width / n_col as f64;
-        let cell_height = height / n_row as f64;
-
-        let cells: Vec<HashSet<usize>> = vec![HashSet::new(); n_col * n_row];
-
-        UniformGrid {
-            cells,
-            cell_width,
-            cell_height,
-            boundary,
-            n_col,
-            n_row,
-        }
+        UniformGrid::with_cell_size(boundary, cell_size)
     }
 
     pub fn with_cell_size(boundary: Rectangle, cell_size: f64) -> UniformGrid {
         let width = boundary.width();
         let height = boundary.height();
 
-        let n_col = (width / cell_size).floor().max(1.0) as usize;
-        let n_row = (height / cell_size).floor().max(1.0) as usize;
+        // in floats, so a tiny cell size can't overflow before the cap
+        let max_cells = MAX_CELLS as f64;
+        let mut cols = (width / cell_size).floor().max(1.0).min(max_cells);
+        let mut rows = (height / cell_size).floor().max(1.0).min(max_cells);
+        if cols * rows > max_cells {
+            // larger cells only make the neighbour lists longer
+            let shrink = (cols * rows / max_cells).sqrt();
+            cols = (cols / shrink).floor().max(1.0);
+            rows = (max_cells / cols).floor().min(rows);
+        }
+        let n_col = cols as usize;
+        let n_row = rows as usize;
 
         let cell_width = width / n_col as f64;
         let cell_height = height / n_row as f64;
 
-        let cells = vec![HashSet::new(); n_col * n_row];
+        let cells = vec![Vec::new(); n_col * n_row];
 
         UniformGrid {
             cells,
@@ -70,10 +63,16 @@ impl UniformGrid {
     }
 
     pub fn get_close_colliders(&self, position: Vector2) -> Vec<usize> {
-        let (col, row) = self.get_cell_indices(position);
+        let mut indices = Vec::new();
+        self.collect_close_colliders(position, &mut indices);
+        indices
+    }
 
-        let mut indices = HashSet::new();
+    /// Like `get_close_colliders` but reuses the given buffer.
+    pub fn collect_close_colliders(&self, position: Vector2, indices: &mut Vec<usize>) {
+        let (col, row) = self.get_cell_indices(position);
 
+        indices.clear();
         for dc in -1..=1 {
             for dr in -1..=1 {
                 if let Some(idx) = self.get_cell_index_safe(col as isize + dc, row as isize + dr) {
@@ -81,22 +80,29 @@ impl UniformGrid {
                 }
             }
         }
-
-        indices.into_iter().collect()
     }
 
-    pub fn add_particle(&mut self, index: usize, particle: &Particle) {
-        let (col, row) = self.get_cell_indices(particle.position);
+    pub fn add_particle(&mut self, index: usize, position: Vector2) {
+        let (col, row) = self.get_cell_indices(position);
         let cell_index = self.get_cell_index(col, row);
-        self.cells[cell_index].insert(index);
+        self.cells[cell_index].push(index);
     }
 
-    fn get_cell_indices(&self, position: Vector2) -> (usize, usize) {
+    /// Grid with cells as large as the biggest particle containing all particles.
+    pub fn from_store(boundary: Rectangle, particles: &ParticleStore) -> UniformGrid {
+        let mut grid = UniformGrid::new(boundary, particles.max_radius());
+        for (index, &position) in particles.positions.iter().enumerate() {
+            grid.add_particle(index, position);
+        }
+        grid
+    }
+
+    pub fn get_cell_indices(&self, position: Vector2) -> (usize, usize) {
         let col = ((position.x - self.boundary.min.x) / self.cell_width).floor() as usize;
         let row = ((position.y - self.boundary.min.y) / self.cell_height).floor() as usize;
 
-        let col = col.clamp(0, self.n_col - 1) as usize;
-        let row = row.clamp(0, self.n_row - 1) as usize;
+        let col = col.clamp(0, self.n_col - 1);
+        let row = row.clamp(0, self.n_row - 1);
 
         (col, row)
     }
@@ -122,19 +128,24 @@ impl UniformGrid {
     /// Returns error if not possible.
     pub fn try_get_none_overlaping_position(
         &mut self,
+        rng: &mut SimRng,
         particle_radius: f64,
-        particles: &Vec<Particle>,
+        particles: &[Particle],
         max_attempts_per_particle: usize,
     ) -> Result<Vector2, String> {
         if particle_radius > self.cell_height {
             return Err("Radius is greater than the grids.".to_string());
         }
+        let (min, max) = (
+            self.boundary.min + particle_radius,
+            self.boundary.max - particle_radius,
+        );
+        if min.x >= max.x || min.y >= max.y {
+            return Err("Radius is too large for the region.".to_string());
+        }
 
         for _ in 0..max_attempts_per_particle {
-            let position = Vector2::random_min_max(
-                self.boundary.min + particle_radius,
-                self.boundary.max - particle_radius,
-            );
+            let position = Vector2::random_min_max(rng, min, max);
             let neighbours = self.get_close_colliders(position);
             let mut overlaps = false;
             for idx in neighbours {
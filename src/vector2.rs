@@ -1,10 +1,4 @@
 use rand::prelude::*;
-use rand_pcg::Pcg64Mcg;
-use std::cell::UnsafeCell;
-
-thread_local! {
-    static RNG: UnsafeCell<Pcg64Mcg> = UnsafeCell::new(Pcg64Mcg::seed_from_u64(12345));
-}
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Vector2 {
@@ -117,69 +111,55 @@ impl Vector2 {
         self / self.length()
     }
 
-    pub fn random_in_square(range: std::ops::Range<f64>) -> Vector2 {
-        RNG.with(|cell| {
-            let rng = unsafe { &mut *cell.get() };
-            Vector2 {
-                x: rng.random_range(range.clone()),
-                y: rng.random_range(range),
-            }
-        })
+    pub fn random_in_square(rng: &mut impl Rng, range: std::ops::Range<f64>) -> Vector2 {
+        Vector2 {
+            x: rng.random_range(range.clone()),
+            y: rng.random_range(range),
+        }
     }
 
     pub fn random_in_rectangle(
+        rng: &mut impl Rng,
         x_range: std::ops::Range<f64>,
         y_range: std::ops::Range<f64>,
     ) -> Vector2 {
-        RNG.with(|cell| {
-            let rng = unsafe { &mut *cell.get() };
-            Vector2 {
-                x: rng.random_range(x_range),
-                y: rng.random_range(y_range),
-            }
-        })
+        Vector2 {
+            x: rng.random_range(x_range),
+            y: rng.random_range(y_range),
+        }
     }
 
-    pub fn random_min_max(min: Vector2, max: Vector2) -> Vector2 {
-        RNG.with(|cell| {
-            let rng = unsafe { &mut *cell.get() };
-            Vector2 {
-                x: rng.random_range(min.x..max.x),
-                y: rng.random_range(min.y..max.y),
-            }
-        })
+    pub fn random_min_max(rng: &mut impl Rng, min: Vector2, max: Vector2) -> Vector2 {
+        Vector2 {
+            x: rng.random_range(min.x..max.x),
+            y: rng.random_range(min.y..max.y),
+        }
     }
 
-    pub fn random_in_disk() -> Vector2 {
-        RNG.with(|cell| {
-            loop {
-                let rng = unsafe { &mut *cell.get() };
-                let v = Vector2 {
-                    x: rng.random_range(-1.0..1.0),
-                    y: rng.random_range(-1.0..1.0),
-                };
-                if v.length_squared() < 1.0 {
-                    return v;
-                }
+    pub fn random_in_disk(rng: &mut impl Rng) -> Vector2 {
+        loop {
+            let v = Vector2 {
+                x: rng.random_range(-1.0..1.0),
+                y: rng.random_range(-1.0..1.0),
+            };
+            if v.length_squared() < 1.0 {
+                return v;
             }
-        })
+        }
     }
 
-    pub fn random_gaussian(mean: f64, std_dev: f64) -> Vector2 {
+    pub fn random_gaussian(rng: &mut impl Rng, mean: f64, std_dev: f64) -> Vector2 {
         use rand_distr::Normal;
 
-        RNG.with(|cell| {
-            let rng = unsafe { &mut *cell.get() };
-            let normal = Normal::new(mean, std_dev).unwrap();
-            let x: f64 = normal.sample(rng);
-            let y: f64 = normal.sample(rng);
+        let normal = Normal::new(mean, std_dev).unwrap();
+        let x: f64 = normal.sample(rng);
+        let y: f64 = normal.sample(rng);
 
-            Vector2 { x, y }
-        })
+        Vector2 { x, y }
     }
 
     pub fn reflect(self, n: Vector2) -> Vector2 {
-        return self - 2.0 * dot(self, n) * n;
+        self - 2.0 * dot(self, n) * n
     }
 
     pub fn lerp(origin: Vector2, target: Vector2, t: f64) -> Vector2 {
@@ -188,9 +168,32 @@ impl Vector2 {
             y: origin.y + (target.y - origin.y) * t,
         }
     }
+
+    /// Counter-clockwise rotation by `angle` radians.
+    pub fn rotated(self, angle: f64) -> Vector2 {
+        let (sin, cos) = angle.sin_cos();
+        Vector2 {
+            x: cos * self.x - sin * self.y,
+            y: sin * self.x + cos * self.y,
+        }
+    }
+
+    /// The vector rotated by 90 degrees counter-clockwise.
+    pub fn perp(self) -> Vector2 {
+        Vector2 {
+            x: -self.y,
+            y: self.x,
+        }
+    }
 }
 
 #[inline]
 pub fn dot(v1: Vector2, v2: Vector2) -> f64 {
     v1.x * v2.x + v1.y * v2.y
 }
+
+/// z component of the 3d cross product.
+#[inline]
+pub fn cross(v1: Vector2, v2: Vector2) -> f64 {
+    v1.x * v2.y - v1.y * v2.x
+}
@@ -0,0 +1,66 @@
+use crate::core::Rectangle;
+use crate::vector2::Vector2;
+
+/// Maps the `view` rectangle of the simulation onto a `width` x `height`
+/// pixel area with y pointing down, shared by the window and the offscreen
+/// renderer.
+///
+/// x and y are scaled alike so circles stay round. The view is centered and
+/// fills the area along one axis, leaving bars along the other one when the
+/// aspect ratios differ.
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pub view: Rectangle,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Pixel size for showing `view` with its aspect ratio, as large as fits
+/// into 1000 x 700.
+pub fn default_size(view: Rectangle) -> (usize, usize) {
+    let scale = (1000.0 / view.width()).min(700.0 / view.height());
+    (
+        (scale * view.width()).round() as usize,
+        (scale * view.height()).round() as usize,
+    )
+}
+
+impl Viewport {
+    pub fn new(view: Rectangle, width: f64, height: f64) -> Viewport {
+        Viewport {
+            view,
+            width,
+            height,
+        }
+    }
+
+    /// Pixels per simulation unit, negative in y.
+    pub fn scale(self) -> Vector2 {
+        let s = (self.width / self.view.width()).min(self.height / self.view.height());
+        Vector2::new(s, -s)
+    }
+
+    pub fn to_screen(self, p: Vector2) -> Vector2 {
+        self.scale() * p + self.translation()
+    }
+
+    /// Inverse of `to_screen`, e.g. for the mouse position.
+    pub fn to_world(self, screen: Vector2) -> Vector2 {
+        (screen - self.translation()) / self.scale()
+    }
+
+    /// The pixels the view covers, `min` is the top left corner.
+    pub fn screen_rect(self) -> Rectangle {
+        Rectangle {
+            min: self.to_screen(Vector2::new(self.view.min.x, self.view.max.y)),
+            max: self.to_screen(Vector2::new(self.view.max.x, self.view.min.y)),
+        }
+    }
+
+    fn translation(self) -> Vector2 {
+        let center = (self.view.min + self.view.max) / 2.0;
+        Vector2::new(self.width, self.height) / 2.0 - self.scale() * center
+    }
+}
+
+// S * C + T = screen / 2, with C the center of the view and S = (s, -s)
@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::headless::Observer;
+use crate::headless::output_base;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+/// Writes legacy VTK files for ParaView, one pair per frame.
+///
+/// For a base path `out/run` frame `n` goes to `out/run_n.vtk`, the particles
+/// as a point cloud, and `out/run_field_n.vtk`, the density and velocity
+/// binned on a regular grid. `out/run.pvd` and `out/run_field.pvd` collect the
+/// frames into time series and are rewritten after every frame.
+pub struct VtkWriter {
+    directory: PathBuf,
+    stem: String,
+    field_resolution: usize, // cells along the longer side of the boundary
+    frames: Vec<(f64, String, String)>, // time, particle file, field file
+}
+
+impl VtkWriter {
+    pub fn create(base: &Path) -> io::Result<VtkWriter> {
+        let (directory, stem) = output_base(base)?;
+        Ok(VtkWriter {
+            directory,
+            stem,
+            field_resolution: 64,
+            frames: Vec::new(),
+        })
+    }
+
+    pub fn with_field_resolution(mut self, cells: usize) -> VtkWriter {
+        self.field_resolution = cells.max(1);
+        self
+    }
+
+    pub fn write_frame(&mut self, sim: &Simulation) -> io::Result<()> {
+        let frame = self.frames.len();
+        let particle_file = format!("{}_{:05}.vtk", self.stem, frame);
+        let field_file = format!("{}_field_{:05}.vtk", self.stem, frame);
+
+        let mut out = BufWriter::new(File::create(self.directory.join(&particle_file))?);
+        write_particles(&mut out, sim)?;
+        out.flush()?;
+
+        let mut out = BufWriter::new(File::create(self.directory.join(&field_file))?);
+        write_field(&mut out, &Field::bin(sim, self.field_resolution), sim)?;
+        out.flush()?;
+
+        self.frames.push((sim.time, particle_file, field_file));
+        self.write_collection(&format!("{}.pvd", self.stem), sim, |f| &f.1)?;
+        self.write_collection(&format!("{}_field.pvd", self.stem), sim, |f| &f.2)
+    }
+
+    fn write_collection(
+        &self,
+        name: &str,
+        sim: &Simulation,
+        file: impl Fn(&(f64, String, String)) -> &String,
+    ) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(self.directory.join(name))?);
+        writeln!(out, "<?xml version=\"1.0\"?>")?;
+        writeln!(
+            out,
+            "<!-- simple_fluids seed {} stream {} -->",
+            sim.rng.seed(),
+            sim.rng.stream()
+        )?;
+        writeln!(out, "<VTKFile type=\"Collection\" version=\"0.1\">")?;
+        writeln!(out, "  <Collection>")?;
+        for frame in &self.frames {
+            writeln!(
+                out,
+                "    <DataSet timestep=\"{}\" file=\"{}\"/>",
+                frame.0,
+                file(frame)
+            )?;
+        }
+        writeln!(out, "  </Collection>")?;
+        writeln!(out, "</VTKFile>")?;
+        out.flush()
+    }
+}
+
+impl Observer for VtkWriter {
+    fn observe(&mut self, sim: &Simulation) -> io::Result<()> {
+        self.write_frame(sim)
+    }
+}
+
+fn write_header(
+    out: &mut impl Write,
+    sim: &Simulation,
+    title: &str,
+    dataset: &str,
+) -> io::Result<()> {
+    writeln!(out, "# vtk DataFile Version 3.0")?;
+    writeln!(
+        out,
+        "simple_fluids {} step {} seed {} stream {}",
+        title,
+        sim.steps,
+        sim.rng.seed(),
+        sim.rng.stream()
+    )?;
+    writeln!(out, "ASCII")?;
+    writeln!(out, "DATASET {}", dataset)?;
+    // picked up by ParaView as the time of the data set
+    writeln!(out, "FIELD FieldData 1")?;
+    writeln!(out, "TIME 1 1 double")?;
+    writeln!(out, "{}", sim.time)
+}
+
+/// The particles as vertices with their properties as point data.
+fn write_particles(out: &mut impl Write, sim: &Simulation) -> io::Result<()> {
+    let p = &sim.particles;
+    let n = p.len();
+    write_header(out, sim, "particles", "POLYDATA")?;
+
+    writeln!(out, "POINTS {} double", n)?;
+    for x in &p.positions {
+        writeln!(out, "{} {} 0", x.x, x.y)?;
+    }
+    writeln!(out, "VERTICES {} {}", n, 2 * n)?;
+    for i in 0..n {
+        writeln!(out, "1 {}", i)?;
+    }
+
+    writeln!(out, "POINT_DATA {}", n)?;
+    writeln!(out, "SCALARS id long 1")?;
+    writeln!(out, "LOOKUP_TABLE default")?;
+    for id in &p.ids {
+        writeln!(out, "{}", id.0)?;
+    }
+    write_vectors(out, "velocity", &p.velocities)?;
+    write_scalars(out, "radius", &p.radii)?;
+    write_scalars(out, "mass", &p.masses)?;
+    writeln!(out, "COLOR_SCALARS color 4")?;
+    for c in &p.colors {
+        writeln!(out, "{} {} {} {}", c.r, c.g, c.b, c.a)?;
+    }
+    Ok(())
+}
+
+fn write_field(out: &mut impl Write, field: &Field, sim: &Simulation) -> io::Result<()> {
+    write_header(out, sim, "field", "STRUCTURED_POINTS")?;
+    writeln!(out, "DIMENSIONS {} {} 1", field.nx, field.ny)?;
+    writeln!(out, "ORIGIN {} {} 0", field.origin.x, field.origin.y)?;
+    writeln!(out, "SPACING {} {} 1", field.spacing.x, field.spacing.y)?;
+    writeln!(out, "POINT_DATA {}", field.nx * field.ny)?;
+    write_scalars(out, "density", &field.density)?;
+    write_vectors(out, "velocity", &field.velocity)
+}
+
+fn write_scalars(out: &mut impl Write, name: &str, values: &[f64]) -> io::Result<()> {
+    writeln!(out, "SCALARS {} double 1", name)?;
+    writeln!(out, "LOOKUP_TABLE default")?;
+    for value in values {
+        writeln!(out, "{}", value)?;
+    }
+    Ok(())
+}
+
+fn write_vectors(out: &mut impl Write, name: &str, values: &[Vector2]) -> io::Result<()> {
+    writeln!(out, "VECTORS {} double", name)?;
+    for v in values {
+        writeln!(out, "{} {} 0", v.x, v.y)?;
+    }
+    Ok(())
+}
+
+/// Mass density and mass weighted mean velocity per cell, x varies fastest.
+struct Field {
+    nx: usize,
+    ny: usize,
+    origin: Vector2, // center of the first cell
+    spacing: Vector2,
+    density: Vec<f64>,
+    velocity: Vec<Vector2>,
+}
+
+impl Field {
+    fn bin(sim: &Simulation, resolution: usize) -> Field {
+        let b = sim.boundary;
+        let cell = b.width().max(b.height()) / resolution as f64;
+        let nx = (b.width() / cell).round().max(1.0) as usize;
+        let ny = (b.height() / cell).round().max(1.0) as usize;
+        let spacing = Vector2::new(b.width() / nx as f64, b.height() / ny as f64);
+
+        let mut mass = vec![0.0; nx * ny];
+        let mut momentum = vec![Vector2::ZERO; nx * ny];
+        let p = &sim.particles;
+        for i in 0..p.len() {
+            let offset = (p.positions[i] - b.min) / spacing;
+            let col = (offset.x.floor().max(0.0) as usize).min(nx - 1);
+            let row = (offset.y.floor().max(0.0) as usize).min(ny - 1);
+            mass[row * nx + col] += p.masses[i];
+            momentum[row * nx + col] += p.masses[i] * p.velocities[i];
+        }
+
+        let area = spacing.x * spacing.y;
+        Field {
+            nx,
+            ny,
+            origin: b.min + spacing / 2.0,
+            spacing,
+            density: mass.iter().map(|m| m / area).collect(),
+            velocity: momentum
+                .iter()
+                .zip(&mass)
+                .map(|(&q, &m)| if m > 0.0 { q / m } else { Vector2::ZERO })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::color::GREEN;
+use crate::core::Particle;
+use crate::core::ParticleId;
+use crate::core::Rectangle;
+use crate::headless::Observer;
+use crate::particle_store::ParticleStore;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+/// Writes a trajectory in the extended XYZ format read by OVITO, ASE and VMD.
+///
+/// Every frame lists all particles with their id, position, velocity, radius,
+/// mass and color. Particles are 2D, so z is 0 and the cell is one unit thick.
+/// Particles of the same color share a species, named A, B, C, ... in the
+/// order the colors first show up. The ids of particles with a trail are
+/// listed in a `Tracked` key so a replay can draw the trails again.
+pub struct XyzWriter<W: Write> {
+    out: W,
+    colors: Vec<Color>, // index is the species
+}
+
+impl XyzWriter<BufWriter<File>> {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(XyzWriter::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> XyzWriter<W> {
+    pub fn new(out: W) -> Self {
+        XyzWriter {
+            out,
+            colors: Vec::new(),
+        }
+    }
+
+    pub fn write_frame(&mut self, sim: &Simulation) -> io::Result<()> {
+        let p = &sim.particles;
+        let b = sim.boundary;
+        writeln!(self.out, "{}", p.len())?;
+        write!(
+            self.out,
+            "Lattice=\"{} 0 0 0 {} 0 0 0 1\" Origin=\"{} {} -0.5\" pbc=\"F F F\" \
+             Properties=species:S:1:id:I:1:pos:R:3:velo:R:3:radius:R:1:mass:R:1:color:R:3 \
+             Time={} Step={} Seed={} Stream={}",
+            b.width(),
+            b.height(),
+            b.min.x,
+            b.min.y,
+            sim.time,
+            sim.steps,
+            sim.rng.seed(),
+            sim.rng.stream()
+        )?;
+        if !sim.trails.is_empty() {
+            let mut tracked: Vec<ParticleId> = sim.trails.keys().copied().collect();
+            tracked.sort();
+            let ids: Vec<String> = tracked.iter().map(|id| id.0.to_string()).collect();
+            write!(self.out, " Tracked=\"{}\"", ids.join(" "))?;
+        }
+        writeln!(self.out)?;
+
+        for i in 0..p.len() {
+            let species = self.species(p.colors[i]);
+            let (x, v, c) = (p.positions[i], p.velocities[i], p.colors[i]);
+            writeln!(
+                self.out,
+                "{} {} {} {} 0 {} {} 0 {} {} {} {} {}",
+                species_name(species),
+                p.ids[i].0,
+                x.x,
+                x.y,
+                v.x,
+                v.y,
+                p.radii[i],
+                p.masses[i],
+                c.r,
+                c.g,
+                c.b
+            )?;
+        }
+        // keep the file readable while a long run is still going
+        self.out.flush()
+    }
+
+    fn species(&mut self, color: Color) -> usize {
+        match self.colors.iter().position(|&c| c == color) {
+            Some(index) => index,
+            None => {
+                self.colors.push(color);
+                self.colors.len() - 1
+            }
+        }
+    }
+}
+
+impl<W: Write> Observer for XyzWriter<W> {
+    fn observe(&mut self, sim: &Simulation) -> io::Result<()> {
+        self.write_frame(sim)
+    }
+
+    fn finish(&mut self, _sim: &Simulation) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A, B, ..., Z, then A1, B1, ...
+fn species_name(index: usize) -> String {
+    let letter = (b'A' + (index % 26) as u8) as char;
+    match index / 26 {
+        0 => letter.to_string(),
+        round => format!("{}{}", letter, round),
+    }
+}
+
+/// A trajectory read back from an extended XYZ file.
+pub struct Trajectory {
+    pub frames: Vec<Frame>,
+    pub tracked: Vec<ParticleId>, // particles with a trail, sorted
+}
+
+pub struct Frame {
+    pub time: f64,
+    pub step: u64,
+    pub boundary: Rectangle,
+    pub particles: ParticleStore,
+    indices: HashMap<ParticleId, usize>, // index of each id in `particles`
+}
+
+impl Frame {
+    /// Index of the particle with `id` in this frame.
+    pub fn index_of(&self, id: ParticleId) -> Option<usize> {
+        self.indices.get(&id).copied()
+    }
+}
+
+impl Trajectory {
+    pub fn load(path: &Path) -> io::Result<Trajectory> {
+        Trajectory::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads all frames. Only `pos` is required in the properties, `id`,
+    /// `velo`, `radius`, `mass` and `color` are used when they are there.
+    /// Without `Lattice` the boundary is the box around the particles, without
+    /// `Time` and `Step` the frames count as one second and step apart.
+    pub fn read(input: impl BufRead) -> io::Result<Trajectory> {
+        let mut lines = Lines {
+            lines: input.lines(),
+            number: 0,
+        };
+        let mut frames = Vec::new();
+        let mut tracked = Vec::new();
+        while let Some(line) = lines.next()? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let count: usize = line
+                .trim()
+                .parse()
+                .map_err(|_| lines.error("expected the number of particles"))?;
+            let comment = lines
+                .next()?
+                .ok_or_else(|| lines.error("missing comment line"))?;
+            let header = parse_comment(&comment).map_err(|err| lines.error(err))?;
+            let columns = match header.get("Properties") {
+                Some(spec) => Columns::parse(spec).map_err(|err| lines.error(err))?,
+                None => Columns::parse("species:S:1:pos:R:3").unwrap(),
+            };
+
+            let mut particles = ParticleStore::default();
+            for index in 0..count {
+                let line = lines
+                    .next()?
+                    .ok_or_else(|| lines.error("the frame ends early"))?;
+                let particle = columns
+                    .particle(&line, index)
+                    .map_err(|err| lines.error(err))?;
+                particles.push(particle);
+            }
+
+            let frame_index = frames.len();
+            let boundary = match boundary(&header).map_err(|err| lines.error(err))? {
+                Some(boundary) => boundary,
+                None => bounding_box(&particles),
+            };
+            if let Some(ids) = header.get("Tracked") {
+                for id in ids.split_whitespace() {
+                    let id = id
+                        .parse()
+                        .map_err(|_| lines.error(format!("invalid tracked id {}", id)))?;
+                    tracked.push(ParticleId(id));
+                }
+            }
+            frames.push(Frame {
+                time: number(&header, "Time")
+                    .map_err(|err| lines.error(err))?
+                    .unwrap_or(frame_index as f64),
+                step: number(&header, "Step")
+                    .map_err(|err| lines.error(err))?
+                    .map_or(frame_index as u64, |step| step as u64),
+                boundary,
+                indices: particles
+                    .ids
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &id)| (id, index))
+                    .collect(),
+                particles,
+            });
+        }
+
+        if frames.is_empty() {
+            return Err(invalid_data("no frames".to_string()));
+        }
+        tracked.sort();
+        tracked.dedup();
+        Ok(Trajectory { frames, tracked })
+    }
+
+    /// Index of the last frame at or before `time`, the first frame before it.
+    pub fn frame_at(&self, time: f64) -> usize {
+        self.frames
+            .partition_point(|frame| frame.time <= time)
+            .saturating_sub(1)
+    }
+
+    /// Positions of the particle in the frames up to `frame`, at most `length`
+    /// of them. Frames without the particle end the trail.
+    pub fn trail(&self, id: ParticleId, frame: usize, length: usize) -> Vec<Vector2> {
+        let mut trail = Vec::new();
+        for frame in self.frames[..=frame].iter().rev().take(length) {
+            match frame.index_of(id) {
+                Some(index) => trail.push(frame.particles.positions[index]),
+                None => break,
+            }
+        }
+        trail.reverse();
+        trail
+    }
+}
+
+/// Lines with the number of the last one for error messages.
+struct Lines<R> {
+    lines: io::Lines<R>,
+    number: usize,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn next(&mut self) -> io::Result<Option<String>> {
+        match self.lines.next() {
+            Some(line) => {
+                self.number += 1;
+                line.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> io::Error {
+        invalid_data(format!("line {}: {}", self.number, message))
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Splits the comment line into `key=value` pairs, values may be quoted.
+/// Words without a value are skipped.
+fn parse_comment(line: &str) -> Result<HashMap<String, String>, String> {
+    let mut values = HashMap::new();
+    let mut rest = line.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.split_whitespace().last().unwrap_or_default();
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted
+                .split_once('"')
+                .ok_or_else(|| format!("unterminated quote after {}=", key))?,
+            None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+        };
+        values.insert(key.to_string(), value.to_string());
+        rest = after.trim_start();
+    }
+    Ok(values)
+}
+
+fn number(header: &HashMap<String, String>, key: &str) -> Result<Option<f64>, String> {
+    header
+        .get(key)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid {} {}", key, value))
+        })
+        .transpose()
+}
+
+fn numbers(header: &HashMap<String, String>, key: &str) -> Result<Option<Vec<f64>>, String> {
+    header
+        .get(key)
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(|x| x.parse().map_err(|_| format!("invalid {} {}", key, value)))
+                .collect()
+        })
+        .transpose()
+}
+
+/// The x and y extent of the cell, starting at `Origin` if given.
+fn boundary(header: &HashMap<String, String>) -> Result<Option<Rectangle>, String> {
+    let Some(lattice) = numbers(header, "Lattice")? else {
+        return Ok(None);
+    };
+    if lattice.len() != 9 {
+        return Err("Lattice needs 9 numbers".to_string());
+    }
+    let origin = match numbers(header, "Origin")? {
+        Some(origin) if origin.len() == 3 => Vector2::new(origin[0], origin[1]),
+        Some(_) => return Err("Origin needs 3 numbers".to_string()),
+        None => Vector2::ZERO,
+    };
+    Ok(Some(Rectangle {
+        min: origin,
+        max: origin + Vector2::new(lattice[0], lattice[4]),
+    }))
+}
+
+fn bounding_box(particles: &ParticleStore) -> Rectangle {
+    if particles.is_empty() {
+        return Rectangle {
+            min: Vector2::ZERO,
+            max: Vector2::new(1.0, 1.0),
+        };
+    }
+    let mut min = Vector2::new(f64::INFINITY, f64::INFINITY);
+    let mut max = Vector2::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for (&x, &r) in particles.positions.iter().zip(&particles.radii) {
+        min = Vector2::new(min.x.min(x.x - r), min.y.min(x.y - r));
+        max = Vector2::new(max.x.max(x.x + r), max.y.max(x.y + r));
+    }
+    Rectangle { min, max }
+}
+
+/// Where the properties start in a particle line, from `Properties`.
+struct Columns {
+    id: Option<usize>,
+    pos: usize,
+    velo: Option<usize>,
+    radius: Option<usize>,
+    mass: Option<usize>,
+    color: Option<usize>,
+    count: usize, // columns per line
+}
+
+impl Columns {
+    fn parse(spec: &str) -> Result<Columns, String> {
+        let fields: Vec<&str> = spec.split(':').collect();
+        if !fields.len().is_multiple_of(3) {
+            return Err(format!("invalid Properties {}", spec));
+        }
+        let mut columns = Columns {
+            id: None,
+            pos: usize::MAX,
+            velo: None,
+            radius: None,
+            mass: None,
+            color: None,
+            count: 0,
+        };
+        for property in fields.chunks(3) {
+            let width: usize = property[2]
+                .parse()
+                .map_err(|_| format!("invalid Properties {}", spec))?;
+            let start = columns.count;
+            match (property[0], width) {
+                ("id", 1) => columns.id = Some(start),
+                ("pos", 2 | 3) => columns.pos = start,
+                ("velo", 2 | 3) => columns.velo = Some(start),
+                ("radius", 1) => columns.radius = Some(start),
+                ("mass" | "masses", 1) => columns.mass = Some(start),
+                ("color", 3 | 4) => columns.color = Some(start),
+                _ => {}
+            }
+            columns.count += width;
+        }
+        if columns.pos == usize::MAX {
+            return Err("Properties has no pos".to_string());
+        }
+        Ok(columns)
+    }
+
+    /// The particle on `line`, `index` is its id if there is no id column.
+    fn particle(&self, line: &str, index: usize) -> Result<Particle, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < self.count {
+            return Err(format!(
+                "expected {} columns, got {}",
+                self.count,
+                fields.len()
+            ));
+        }
+        let value = |column: usize| -> Result<f64, String> {
+            fields[column]
+                .parse()
+                .map_err(|_| format!("invalid number {}", fields[column]))
+        };
+        let vector = |column: usize| -> Result<Vector2, String> {
+            Ok(Vector2::new(value(column)?, value(column + 1)?))
+        };
+
+        Ok(Particle {
+            id: match self.id {
+                Some(column) => ParticleId(
+                    fields[column]
+                        .parse()
+                        .map_err(|_| format!("invalid id {}", fields[column]))?,
+                ),
+                None => ParticleId(index as u64),
+            },
+            position: vector(self.pos)?,
+            velocity: self.velo.map(vector).transpose()?.unwrap_or(Vector2::ZERO),
+            radius: self.radius.map(value).transpose()?.unwrap_or(0.5),
+            mass: self.mass.map(value).transpose()?.unwrap_or(1.0),
+            color: match self.color {
+                Some(column) => Color::new(
+                    value(column)? as f32,
+                    value(column + 1)? as f32,
+                    value(column + 2)? as f32,
+                    1.0,
+                ),
+                None => GREEN,
+            },
+            ..Default::default()
+        })
+    }
+}
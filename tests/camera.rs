@@ -0,0 +1,27 @@
+#![cfg(feature = "render")]
+
+use simple_fluid_simulation::camera::Camera;
+use simple_fluid_simulation::core::Rectangle;
+use simple_fluid_simulation::vector2::Vector2;
+
+// endless zooming used to shrink the view to nothing or grow it to infinity
+#[test]
+fn zoom_stays_within_limits() {
+    let home = Rectangle {
+        min: Vector2::new(0.0, 0.0),
+        max: Vector2::new(2.0, 1.0),
+    };
+    let center = Vector2::new(0.7, 0.3);
+    let mut camera = Camera::new(home);
+    for _ in 0..10_000 {
+        camera.zoom(center, 0.5);
+    }
+    assert!((camera.view.width() - home.width() / 1000.0).abs() < 1e-12);
+    assert!(camera.view.min.x < center.x && center.x < camera.view.max.x);
+
+    for _ in 0..10_000 {
+        camera.zoom(center, 2.0);
+    }
+    assert!((camera.view.width() - home.width() * 10.0).abs() < 1e-9);
+    assert!(camera.view.height().is_finite());
+}
@@ -0,0 +1,42 @@
+use simple_fluid_simulation::constraint::Constraint;
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::core::ParticleId;
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::vector2::Vector2;
+
+fn particle(x: f64) -> Particle {
+    Particle {
+        mass: 1.0,
+        position: Vector2::new(x, 0.5),
+        radius: 0.01,
+        ..Default::default()
+    }
+}
+
+// links name particles by id, so moving or removing others can't rewire them
+#[test]
+fn links_follow_their_particles() {
+    let mut sim = Simulation::default();
+    let ids = sim.add_particles((0..10).map(|k| particle(0.05 + 0.09 * k as f64)).collect());
+    sim.constraints
+        .push(Constraint::distance(ids[8], ids[9], 0.09));
+    sim.constraints
+        .push(Constraint::distance(ids[2], ids[3], 0.09));
+
+    sim.remove_particles(|p| p.id == ids[0] || p.id == ids[3]);
+    sim.reorder_particles();
+
+    let links: Vec<(ParticleId, ParticleId)> =
+        sim.constraints.iter().map(|c| c.particles()).collect();
+    assert_eq!(links, vec![(ids[8], ids[9])]);
+}
+
+#[test]
+fn clusters_need_particles_of_the_simulation() {
+    let mut sim = Simulation::default();
+    let ids = sim.add_particles(vec![particle(0.2), particle(0.3)]);
+    assert!(sim.add_cluster(&[ids[0], ParticleId(99)]).is_err());
+    assert_eq!(sim.add_cluster(&ids), Ok(0));
+    assert!(sim.add_cluster(&ids[..1]).is_err());
+    assert_eq!(sim.particles().clusters, vec![Some(0), Some(0)]);
+}
@@ -0,0 +1,42 @@
+use simple_fluid_simulation::simulation_factory::ScenarioParams;
+use simple_fluid_simulation::simulation_factory::find_scenario;
+use simple_fluid_simulation::snapshot::Format;
+use simple_fluid_simulation::snapshot::Snapshot;
+
+const DT: f64 = 0.001;
+const STEPS: usize = 300;
+
+fn snapshot_bytes(scenario: &str, threads: usize) -> Vec<u8> {
+    let scenario = find_scenario(scenario).unwrap();
+    let mut sim = scenario.build(&ScenarioParams::default()).unwrap();
+    sim.threads = threads;
+    for _ in 0..STEPS {
+        sim.update(DT);
+    }
+    let mut bytes = Vec::new();
+    Snapshot::capture(&sim)
+        .write(&mut bytes, Format::Binary)
+        .unwrap();
+    bytes
+}
+
+#[test]
+fn thread_count_does_not_change_results() {
+    for scenario in [
+        "brownian",
+        "sandpile",
+        "chain",
+        "fountain",
+        "vortex",
+        "cluster-l",
+    ] {
+        let serial = snapshot_bytes(scenario, 1);
+        let parallel = snapshot_bytes(scenario, 4);
+        assert!(serial == parallel, "{} differs with 4 threads", scenario);
+    }
+}
+
+#[test]
+fn same_seed_gives_same_results() {
+    assert!(snapshot_bytes("fountain", 1) == snapshot_bytes("fountain", 1));
+}
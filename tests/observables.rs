@@ -0,0 +1,82 @@
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::core::Rectangle;
+use simple_fluid_simulation::observables::CsvRecorder;
+use simple_fluid_simulation::observables::kinetic_energy;
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::vector2::Vector2;
+
+fn two_particles() -> Simulation {
+    let boundary = Rectangle {
+        min: Vector2::new(0.0, 0.0),
+        max: Vector2::new(1.0, 1.0),
+    };
+    let mut sim = Simulation::default();
+    sim.view = boundary;
+    sim.boundary = boundary;
+    sim.gravity = Vector2::ZERO;
+    let ids = sim.add_particles(
+        [(0.3, -1.0), (0.7, 1.0)]
+            .into_iter()
+            .map(|(x, vx)| Particle {
+                mass: 2.0,
+                position: Vector2::new(x, 0.5),
+                velocity: Vector2::new(vx, 0.5),
+                radius: 0.05,
+                ..Default::default()
+            })
+            .collect(),
+    );
+    sim.track(ids[1]);
+    sim
+}
+
+#[test]
+fn csv_has_a_header_and_a_row_per_sample() {
+    let mut sim = two_particles();
+    let tracked = sim.particles().ids[1];
+    let mut out = Vec::new();
+    let mut recorder = CsvRecorder::new(&mut out, "scenario: test\nseed: 3");
+
+    recorder.write_row(&sim).unwrap();
+    let energy = kinetic_energy(sim.particles());
+    for _ in 0..500 {
+        sim.update(0.001);
+    }
+    recorder.write_row(&sim).unwrap();
+    sim.remove_particles(|p| p.id == tracked);
+    recorder.write_row(&sim).unwrap();
+
+    let csv = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        &lines[..3],
+        [
+            "# simple_fluids observables",
+            "# scenario: test",
+            "# seed: 3"
+        ]
+    );
+    assert!(lines[3].starts_with("# units:"));
+    let columns = format!(
+        "time,step,particles,kinetic_energy,momentum_x,momentum_y,temperature,pressure,x_{},y_{}",
+        tracked.0, tracked.0
+    );
+    assert_eq!(lines[4], columns);
+    assert_eq!(lines.len(), 8);
+
+    let rows: Vec<Vec<&str>> = lines[5..].iter().map(|l| l.split(',').collect()).collect();
+    assert!(rows.iter().all(|row| row.len() == 10));
+    assert_eq!(&rows[0][..3], ["0", "0", "2"]);
+    assert_eq!(rows[0][3].parse::<f64>().unwrap(), energy);
+    // opposite velocities in x, equal ones in y
+    assert_eq!(rows[0][4].parse::<f64>().unwrap(), 0.0);
+    assert_eq!(rows[0][5].parse::<f64>().unwrap(), 2.0);
+    assert_eq!(rows[0][7], "", "no pressure before a second sample");
+    assert_eq!(rows[0][8].parse::<f64>().unwrap(), 0.7);
+
+    // both particles bounce off the side walls within the half second
+    assert_eq!(rows[1][1], "500");
+    assert!(rows[1][7].parse::<f64>().unwrap() > 0.0);
+    assert_eq!(rows[2][2], "1");
+    assert_eq!(&rows[2][8..], ["", ""]);
+}
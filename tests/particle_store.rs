@@ -0,0 +1,51 @@
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::simulation_factory::ScenarioParams;
+use simple_fluid_simulation::simulation_factory::find_scenario;
+
+// Debug prints every field, floats exactly
+fn same(a: &Particle, b: &Particle) -> bool {
+    format!("{:?}", a) == format!("{:?}", b)
+}
+
+// every array of the store has to move together, or particles swap attributes
+#[test]
+fn reordering_keeps_particles_whole() {
+    let mut sim = find_scenario("sandpile")
+        .unwrap()
+        .build(&ScenarioParams::default())
+        .unwrap();
+    for _ in 0..200 {
+        sim.update(0.001);
+    }
+    let before: Vec<Particle> = sim.particles().iter().collect();
+
+    sim.reorder_particles();
+
+    assert_eq!(sim.particles().len(), before.len());
+    for p in &before {
+        let moved = sim.particle(p.id).unwrap();
+        assert!(same(p, &moved), "particle {} changed", p.id.0);
+    }
+}
+
+#[test]
+fn removing_keeps_the_rest_in_order() {
+    let mut sim = find_scenario("brownian")
+        .unwrap()
+        .build(&ScenarioParams::default())
+        .unwrap();
+    let before: Vec<Particle> = sim.particles().iter().collect();
+
+    sim.remove_particles(|p| p.id.0 % 3 == 0);
+
+    let kept: Vec<&Particle> = before.iter().filter(|p| p.id.0 % 3 != 0).collect();
+    let after: Vec<Particle> = sim.particles().iter().collect();
+    assert_eq!(after.len(), kept.len());
+    for (a, b) in kept.iter().zip(&after) {
+        assert!(same(a, b), "particle {} changed", a.id.0);
+        assert_eq!(
+            sim.index_of(a.id).map(|i| sim.particles().ids[i]),
+            Some(a.id)
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs::File;
+
+use simple_fluid_simulation::color::GREEN;
+use simple_fluid_simulation::color::LETTERBOX;
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::core::Rectangle;
+use simple_fluid_simulation::raster::Canvas;
+use simple_fluid_simulation::raster::PngSequence;
+use simple_fluid_simulation::raster::render_frame;
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::vector2::Vector2;
+use simple_fluid_simulation::viewport::Viewport;
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 100;
+
+fn one_particle() -> Simulation {
+    let boundary = Rectangle {
+        min: Vector2::new(0.0, 0.0),
+        max: Vector2::new(1.0, 1.0),
+    };
+    let mut sim = Simulation::default();
+    sim.view = boundary;
+    sim.boundary = boundary;
+    sim.add_particle(Particle {
+        mass: 1.0,
+        position: Vector2::new(0.3, 0.6),
+        radius: 0.05,
+        color: GREEN,
+        ..Default::default()
+    });
+    sim
+}
+
+fn rgb(image: &[u8], x: usize, y: usize) -> [u8; 3] {
+    let i = 4 * (y * WIDTH + x);
+    [image[i], image[i + 1], image[i + 2]]
+}
+
+fn rgb8(r: f32, g: f32, b: f32) -> [u8; 3] {
+    [r, g, b].map(|c| (c * 255.0).round() as u8)
+}
+
+#[test]
+fn particles_land_where_the_viewport_maps_them() {
+    let sim = one_particle();
+    let mut canvas = Canvas::new(WIDTH, HEIGHT);
+    render_frame(&sim, &mut canvas);
+    let image = canvas.to_rgba8();
+    assert_eq!(image.len(), 4 * WIDTH * HEIGHT);
+
+    let center = Viewport::new(sim.view, WIDTH as f64, HEIGHT as f64)
+        .to_screen(sim.particles().positions[0]);
+    let (x, y) = (center.x as usize, center.y as usize);
+    assert_eq!((x, y), (80, 40));
+    assert_eq!(rgb(&image, x, y), rgb8(GREEN.r, GREEN.g, GREEN.b));
+    // empty inside the view, the bars outside of it
+    assert_eq!(rgb(&image, 140, 20), [0, 0, 0]);
+    assert_eq!(
+        rgb(&image, 10, 50),
+        rgb8(LETTERBOX.r, LETTERBOX.g, LETTERBOX.b)
+    );
+}
+
+#[test]
+fn png_frames_have_the_requested_size() {
+    let base = std::env::temp_dir().join(format!("raster_test_{}", std::process::id()));
+    let mut frames = PngSequence::create(&base, WIDTH, HEIGHT).unwrap();
+    frames.write_frame(&one_particle()).unwrap();
+
+    let path = base.with_file_name(format!(
+        "{}_00000.png",
+        base.file_name().unwrap().to_str().unwrap()
+    ));
+    let reader = png::Decoder::new(File::open(&path).unwrap())
+        .read_info()
+        .unwrap();
+    let info = reader.info();
+    assert_eq!((info.width, info.height), (WIDTH as u32, HEIGHT as u32));
+    assert!(info.uncompressed_latin1_text[0].text.contains("seed 12345"));
+    std::fs::remove_file(path).unwrap();
+}
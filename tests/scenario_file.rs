@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use simple_fluid_simulation::scenario_file::ScenarioFile;
+use simple_fluid_simulation::scenario_file::load;
+use simple_fluid_simulation::simulation_factory::ScenarioParams;
+
+const VALID: &str = "\
+# a box with one group
+boundary = 0 0 2 1
+gravity = 0 -1   # pulls down
+restitution = 0.5
+
+[group]
+region = 0 0 1 1
+count = 20
+radius = 0.02
+mass = 3
+color = 0.1 0.2 0.3
+velocity = 0.5 0
+track = 0 4
+";
+
+fn parse_error(text: &str) -> String {
+    ScenarioFile::parse(text).unwrap_err()
+}
+
+#[test]
+fn valid_file_builds_the_described_simulation() {
+    let file = ScenarioFile::parse(VALID).unwrap();
+    assert_eq!(file.restitution, 0.5);
+    assert_eq!(file.groups.len(), 1);
+    assert_eq!(file.groups[0].line, 6);
+    assert_eq!(file.groups[0].count, 20);
+
+    let sim = file.build(&ScenarioParams::default()).unwrap();
+    assert_eq!((sim.boundary.max.x, sim.boundary.max.y), (2.0, 1.0));
+    assert_eq!((sim.view.max.x, sim.view.max.y), (2.0, 1.0));
+    assert_eq!((sim.gravity.x, sim.gravity.y), (0.0, -1.0));
+    assert_eq!(sim.restitution, 0.5);
+
+    let particles = sim.particles();
+    assert_eq!(particles.len(), 20);
+    for i in 0..particles.len() {
+        let p = particles.get(i);
+        assert!(p.position.x >= 0.02 && p.position.x <= 0.98);
+        assert!(p.position.y >= 0.02 && p.position.y <= 0.98);
+        assert_eq!((p.velocity.x, p.velocity.y), (0.5, 0.0));
+        assert_eq!((p.radius, p.mass), (0.02, 3.0));
+        assert_eq!((p.color.r, p.color.g, p.color.b), (0.1, 0.2, 0.3));
+    }
+    assert_eq!(sim.trails.len(), 2);
+    assert!(sim.trails.contains_key(&particles.ids[4]));
+}
+
+#[test]
+fn shipped_scenario_loads() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios/two_gases.scenario");
+    let sim = load(&path, &ScenarioParams::default()).unwrap();
+    assert_eq!(sim.particles().len(), 600);
+    assert_eq!(sim.trails.len(), 1);
+}
+
+#[test]
+fn malformed_value_names_its_line() {
+    let text = VALID.replace("radius = 0.02", "radius = 0.o2");
+    assert_eq!(
+        parse_error(&text),
+        "line 9: radius has an invalid number `0.o2`"
+    );
+}
+
+#[test]
+fn unknown_key_names_its_line() {
+    let text = VALID.replace("restitution = 0.5", "restitution = 0.5\nfriction = 0.1");
+    assert_eq!(parse_error(&text), "line 5: unknown key friction");
+
+    let text = VALID.replace("count = 20", "count = 20\nspeed = 2");
+    assert_eq!(parse_error(&text), "line 9: unknown key speed");
+}
+
+#[test]
+fn region_outside_the_boundary_names_its_line() {
+    let text = VALID.replace("region = 0 0 1 1", "region = 1.5 0 2.5 1");
+    assert_eq!(
+        parse_error(&text),
+        "line 7: region must lie inside the boundary"
+    );
+}
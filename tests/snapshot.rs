@@ -0,0 +1,93 @@
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::simulation_factory::ScenarioParams;
+use simple_fluid_simulation::simulation_factory::find_scenario;
+use simple_fluid_simulation::snapshot::Format;
+use simple_fluid_simulation::snapshot::Snapshot;
+
+const DT: f64 = 0.001;
+const STEPS: usize = 200;
+const SCENARIOS: [&str; 5] = ["sandpile", "chain", "fountain", "vortex", "cluster-l"];
+
+fn run(scenario: &str, steps: usize) -> Simulation {
+    let mut sim = build(scenario);
+    for _ in 0..steps {
+        sim.update(DT);
+    }
+    sim
+}
+
+fn build(scenario: &str) -> Simulation {
+    find_scenario(scenario)
+        .unwrap()
+        .build(&ScenarioParams::default())
+        .unwrap()
+}
+
+fn bytes(snapshot: &Snapshot, format: Format) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    snapshot.write(&mut bytes, format).unwrap();
+    bytes
+}
+
+#[test]
+fn snapshots_read_back_unchanged() {
+    for format in [Format::Binary, Format::Text] {
+        for scenario in SCENARIOS {
+            let written = bytes(&Snapshot::capture(&run(scenario, STEPS)), format);
+            let read = Snapshot::read(&mut written.as_slice(), format).unwrap();
+            assert!(
+                bytes(&read, format) == written,
+                "{} changes in {:?}",
+                scenario,
+                format
+            );
+        }
+    }
+}
+
+// the run continued from a snapshot has to match the one that never stopped, bit for bit
+#[test]
+fn resumed_runs_match_uninterrupted_ones() {
+    for format in [Format::Binary, Format::Text] {
+        for scenario in SCENARIOS {
+            let saved = bytes(&Snapshot::capture(&run(scenario, STEPS)), format);
+            let mut resumed = build(scenario);
+            Snapshot::read(&mut saved.as_slice(), format)
+                .unwrap()
+                .apply(&mut resumed)
+                .unwrap();
+            for _ in 0..STEPS {
+                resumed.update(DT);
+            }
+
+            let uninterrupted = run(scenario, 2 * STEPS);
+            assert!(
+                bytes(&Snapshot::capture(&resumed), Format::Binary)
+                    == bytes(&Snapshot::capture(&uninterrupted), Format::Binary),
+                "{} resumed from {:?} differs",
+                scenario,
+                format
+            );
+        }
+    }
+}
+
+#[test]
+fn reused_ids_are_rejected() {
+    let text =
+        String::from_utf8(bytes(&Snapshot::capture(&run("chain", 10)), Format::Text)).unwrap();
+    let read = |text: &str| Snapshot::read(&mut text.as_bytes(), Format::Text);
+    assert!(read(&text).is_ok());
+
+    let duplicate = text.replacen("\np 2 ", "\np 1 ", 1);
+    let err = read(&duplicate).err().unwrap();
+    assert!(
+        err.to_string().contains("particle id 1 appears twice"),
+        "{}",
+        err
+    );
+
+    let next_id = text.replacen("\nnext_id 58\n", "\nnext_id 57\n", 1);
+    let err = read(&next_id).err().unwrap();
+    assert!(err.to_string().contains("not below next_id 57"), "{}", err);
+}
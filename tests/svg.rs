@@ -0,0 +1,66 @@
+use simple_fluid_simulation::constraint::Constraint;
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::core::Rectangle;
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::svg::write_svg;
+use simple_fluid_simulation::vector2::Vector2;
+use simple_fluid_simulation::viewport::Viewport;
+
+fn two_particles() -> Simulation {
+    let boundary = Rectangle {
+        min: Vector2::new(0.0, 0.0),
+        max: Vector2::new(1.0, 1.0),
+    };
+    let mut sim = Simulation::default();
+    sim.view = boundary;
+    sim.boundary = boundary;
+    sim.gravity = Vector2::ZERO;
+    let ids = sim.add_particles(
+        [Vector2::new(0.25, 0.75), Vector2::new(0.75, 0.25)]
+            .into_iter()
+            .map(|position| Particle {
+                mass: 1.0,
+                position,
+                velocity: Vector2::new(0.1, 0.0),
+                radius: 0.05,
+                ..Default::default()
+            })
+            .collect(),
+    );
+    sim.constraints
+        .push(Constraint::spring(ids[0], ids[1], 0.7, 1.0, 0.0));
+    sim.track(ids[0]);
+    for _ in 0..3 {
+        sim.update(0.01);
+    }
+    sim
+}
+
+#[test]
+fn svg_shows_the_view_inside_letterbox_bars() {
+    let sim = two_particles();
+    // a square view in a wide image leaves 50 pixel bars left and right
+    let viewport = Viewport::new(sim.view, 200.0, 100.0);
+    let mut out = Vec::new();
+    write_svg(&mut out, &sim, viewport).unwrap();
+    let svg = String::from_utf8(out).unwrap();
+
+    assert!(
+        svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"100\"")
+    );
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(svg.contains("seed 12345 stream 0"));
+    assert!(svg.contains(
+        "<clipPath id=\"view\"><rect x=\"50.000\" y=\"0.000\" width=\"100.000\" height=\"100.000\"/></clipPath>"
+    ));
+    assert!(svg.contains("<g clip-path=\"url(#view)\">"));
+
+    assert_eq!(svg.matches("<circle ").count(), 2);
+    let center = viewport.to_screen(sim.particles().positions[0]);
+    assert!(svg.contains(&format!(
+        "<circle cx=\"{:.3}\" cy=\"{:.3}\" r=\"5.000\"",
+        center.x, center.y
+    )));
+    assert_eq!(svg.matches("<line ").count(), 1);
+    assert_eq!(svg.matches("<polyline ").count(), 1);
+}
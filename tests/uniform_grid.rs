@@ -0,0 +1,25 @@
+use simple_fluid_simulation::core::Rectangle;
+use simple_fluid_simulation::uniform_grid::UniformGrid;
+use simple_fluid_simulation::vector2::Vector2;
+
+// a cell per nanometre would need terabytes, the grid has to coarsen instead
+#[test]
+fn tiny_cells_are_capped() {
+    let boundary = Rectangle {
+        min: Vector2 { x: 0.0, y: 0.0 },
+        max: Vector2 { x: 2.0, y: 1.0 },
+    };
+    let mut grid = UniformGrid::with_cell_size(boundary, 1e-9);
+    grid.add_particle(0, Vector2 { x: 1.0, y: 0.5 });
+    grid.add_particle(
+        1,
+        Vector2 {
+            x: 1.0 + 1e-9,
+            y: 0.5,
+        },
+    );
+    assert_eq!(
+        grid.get_close_colliders(Vector2 { x: 1.0, y: 0.5 }),
+        vec![0, 1]
+    );
+}
@@ -0,0 +1,96 @@
+use std::fs;
+
+use simple_fluid_simulation::color::Color;
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::core::Rectangle;
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::vector2::Vector2;
+use simple_fluid_simulation::vtk::VtkWriter;
+
+fn three_particles() -> Simulation {
+    let boundary = Rectangle {
+        min: Vector2::new(0.0, 0.0),
+        max: Vector2::new(2.0, 1.0),
+    };
+    let mut sim = Simulation::default();
+    sim.view = boundary;
+    sim.boundary = boundary;
+    sim.gravity = Vector2::ZERO;
+    sim.add_particles(
+        (0..3)
+            .map(|i| Particle {
+                mass: 1.0,
+                position: Vector2::new(0.5 + 0.5 * i as f64, 0.5),
+                radius: 0.05,
+                color: Color::new(0.25 * i as f32, 0.5, 1.0, 1.0),
+                ..Default::default()
+            })
+            .collect(),
+    );
+    sim
+}
+
+/// Lines after `header` up to the next line starting with a letter.
+fn section<'a>(lines: &[&'a str], header: &str) -> Vec<&'a str> {
+    let start = lines.iter().position(|&l| l == header).unwrap() + 1;
+    lines[start..]
+        .iter()
+        .take_while(|l| !l.starts_with(|c: char| c.is_ascii_alphabetic()))
+        .copied()
+        .collect()
+}
+
+#[test]
+fn legacy_files_and_collections_list_every_frame() {
+    let directory = std::env::temp_dir().join(format!("vtk_test_{}", std::process::id()));
+    let mut sim = three_particles();
+    let mut writer = VtkWriter::create(&directory.join("run"))
+        .unwrap()
+        .with_field_resolution(8);
+    writer.write_frame(&sim).unwrap();
+    sim.update(0.01);
+    writer.write_frame(&sim).unwrap();
+
+    let vtk = fs::read_to_string(directory.join("run_00001.vtk")).unwrap();
+    let lines: Vec<&str> = vtk.lines().collect();
+    assert_eq!(lines[0], "# vtk DataFile Version 3.0");
+    assert_eq!(
+        lines[1],
+        "simple_fluids particles step 1 seed 12345 stream 0"
+    );
+    assert_eq!(lines[2], "ASCII");
+    assert_eq!(lines[3], "DATASET POLYDATA");
+
+    let points = section(&lines, "POINTS 3 double");
+    assert_eq!(points.len(), 3);
+    let x = sim.particles().positions[0];
+    assert_eq!(points[0], format!("{} {} 0", x.x, x.y));
+    assert!(lines.contains(&"VERTICES 3 6"));
+    assert!(lines.contains(&"POINT_DATA 3"));
+    let colors = section(&lines, "COLOR_SCALARS color 4");
+    assert_eq!(colors, ["0 0.5 1 1", "0.25 0.5 1 1", "0.5 0.5 1 1"]);
+
+    let field = fs::read_to_string(directory.join("run_field_00001.vtk")).unwrap();
+    assert!(field.contains("DATASET STRUCTURED_POINTS"));
+    assert!(field.contains("POINT_DATA "));
+
+    let pvd = fs::read_to_string(directory.join("run.pvd")).unwrap();
+    let lines: Vec<&str> = pvd.lines().collect();
+    assert_eq!(lines[0], "<?xml version=\"1.0\"?>");
+    assert_eq!(lines[1], "<!-- simple_fluids seed 12345 stream 0 -->");
+    assert_eq!(
+        lines[4..6],
+        [
+            "    <DataSet timestep=\"0\" file=\"run_00000.vtk\"/>",
+            &format!(
+                "    <DataSet timestep=\"{}\" file=\"run_00001.vtk\"/>",
+                sim.time
+            ),
+        ]
+    );
+    let field_pvd = fs::read_to_string(directory.join("run_field.pvd")).unwrap();
+    assert_eq!(field_pvd.matches("<DataSet ").count(), 2);
+    assert!(field_pvd.contains("file=\"run_field_00001.vtk\""));
+
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,127 @@
+use simple_fluid_simulation::color::Color;
+use simple_fluid_simulation::core::Particle;
+use simple_fluid_simulation::core::Rectangle;
+use simple_fluid_simulation::simulation::Simulation;
+use simple_fluid_simulation::vector2::Vector2;
+use simple_fluid_simulation::xyz::Trajectory;
+use simple_fluid_simulation::xyz::XyzWriter;
+
+fn three_particles() -> Simulation {
+    let boundary = Rectangle {
+        min: Vector2::new(-1.0, 0.0),
+        max: Vector2::new(1.0, 1.0),
+    };
+    let mut sim = Simulation::default();
+    sim.view = boundary;
+    sim.boundary = boundary;
+    sim.gravity = Vector2::ZERO;
+    let colors = [
+        Color::new(0.1, 0.2, 0.3, 1.0),
+        Color::new(0.9, 0.8, 0.7, 1.0),
+        Color::new(0.1, 0.2, 0.3, 1.0),
+    ];
+    let ids = sim.add_particles(
+        colors
+            .into_iter()
+            .enumerate()
+            .map(|(i, color)| Particle {
+                mass: 1.0 + i as f64,
+                position: Vector2::new(-0.6 + 0.5 * i as f64, 0.3 + 0.2 * i as f64),
+                velocity: Vector2::new(0.1 * i as f64, -0.2),
+                radius: 0.05,
+                color,
+                ..Default::default()
+            })
+            .collect(),
+    );
+    sim.track(ids[2]);
+    sim
+}
+
+#[test]
+fn frames_read_back_as_written() {
+    let mut sim = three_particles();
+    let mut out = Vec::new();
+    let mut writer = XyzWriter::new(&mut out);
+    let mut written = Vec::new();
+    for _ in 0..3 {
+        writer.write_frame(&sim).unwrap();
+        written.push((sim.time, sim.steps, sim.particles().clone()));
+        for _ in 0..10 {
+            sim.update(0.01);
+        }
+    }
+
+    let trajectory = Trajectory::read(out.as_slice()).unwrap();
+    assert_eq!(trajectory.tracked, [sim.particles().ids[2]]);
+    assert_eq!(trajectory.frames.len(), written.len());
+    for (frame, (time, step, particles)) in trajectory.frames.iter().zip(&written) {
+        assert_eq!((frame.time, frame.step), (*time, *step));
+        assert_eq!((frame.boundary.min.x, frame.boundary.max.y), (-1.0, 1.0));
+        assert_eq!(frame.particles.len(), particles.len());
+        for (i, &id) in particles.ids.iter().enumerate() {
+            let read = frame.particles.get(frame.index_of(id).unwrap());
+            let (position, velocity) = (particles.positions[i], particles.velocities[i]);
+            assert_eq!(read.id, id);
+            assert_eq!((read.position.x, read.position.y), (position.x, position.y));
+            assert_eq!((read.velocity.x, read.velocity.y), (velocity.x, velocity.y));
+            assert_eq!(
+                (read.radius, read.mass),
+                (particles.radii[i], particles.masses[i])
+            );
+            let color = particles.colors[i];
+            assert_eq!(
+                (read.color.r, read.color.g, read.color.b),
+                (color.r, color.g, color.b)
+            );
+        }
+    }
+}
+
+#[test]
+fn ids_follow_particles_added_and_removed_between_frames() {
+    let mut sim = three_particles();
+    let ids = sim.particles().ids.clone();
+    let mut out = Vec::new();
+    let mut writer = XyzWriter::new(&mut out);
+
+    writer.write_frame(&sim).unwrap();
+    // removing the first particle moves the others to lower indices
+    sim.remove_particles(|p| p.id == ids[0]);
+    let added = sim.add_particle(Particle {
+        mass: 1.0,
+        position: Vector2::new(0.5, 0.8),
+        radius: 0.05,
+        ..Default::default()
+    });
+    sim.update(0.01);
+    writer.write_frame(&sim).unwrap();
+    sim.update(0.01);
+    writer.write_frame(&sim).unwrap();
+
+    let trajectory = Trajectory::read(out.as_slice()).unwrap();
+    let frames = &trajectory.frames;
+    assert_eq!(frames[0].index_of(ids[0]), Some(0));
+    assert_eq!(frames[0].index_of(added), None);
+    assert_eq!(frames[1].index_of(ids[0]), None);
+    for frame in frames {
+        for &id in &ids[1..] {
+            let index = frame.index_of(id).unwrap();
+            assert_eq!(frame.particles.ids[index], id);
+        }
+    }
+
+    // the trail follows the id, not the index, and stops where the particle is missing
+    let trail = trajectory.trail(ids[2], 2, 10);
+    let expected: Vec<Vector2> = frames
+        .iter()
+        .map(|frame| frame.particles.positions[frame.index_of(ids[2]).unwrap()])
+        .collect();
+    assert_eq!(trail.len(), 3);
+    for (a, b) in trail.iter().zip(&expected) {
+        assert_eq!((a.x, a.y), (b.x, b.y));
+    }
+    assert_eq!(trajectory.trail(added, 2, 10).len(), 2);
+    assert!(trajectory.trail(ids[0], 2, 10).is_empty());
+    assert_eq!(trajectory.trail(ids[0], 0, 10).len(), 1);
+}